@@ -0,0 +1,40 @@
+//! Lexes generated sources of growing size and reports the time per byte, which stays flat
+//! as long as lexing is linear in the input. Run with `cargo bench --bench lexer`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use zelkel_compiler::lexer::lex;
+
+const SIZES_MB: [usize; 4] = [1, 2, 4, 8];
+const RUNS: usize = 3;
+
+/// A source of at least `size` bytes, mixing every kind of token with non-ASCII text.
+fn generate(size: usize) -> String {
+    let mut source = String::with_capacity(size + 256);
+    let mut n = 0;
+    while source.len() < size {
+        source.push_str(&format!(
+            "fn f{n}(a: int, b: float) -> str {{\n    let c: int = (a << 2) + {n} * 3 % 7;\n    if a >= c && b != 1.5 {{\n        return \"héllo {{a}} ✓ {{b as int}}\";\n    }}\n    return \"ünïcode\";\n}}\n"
+        ));
+        n += 1;
+    }
+    source
+}
+
+fn main() {
+    println!("{:>8} {:>12} {:>10} {:>12}", "size", "tokens", "time", "ns/byte");
+    for mb in SIZES_MB {
+        let source = generate(mb * 1024 * 1024);
+        let mut best = Duration::MAX;
+        let mut tokens = 0;
+        for _ in 0..RUNS {
+            let input = source.clone();
+            let start = Instant::now();
+            let (toks, errors) = lex(black_box(input), "bench.zk".to_string());
+            best = best.min(start.elapsed());
+            assert!(errors.is_empty(), "generated source has errors: {:?}", errors[0]);
+            tokens = toks.len();
+        }
+        println!("{:>5} MB {:>12} {:>8.1?} {:>12.2}", mb, tokens, best, best.as_nanos() as f64 / source.len() as f64);
+    }
+}
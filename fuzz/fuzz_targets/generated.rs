@@ -0,0 +1,28 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zelkel_compiler::cfg::unreachable;
+use zelkel_compiler::lexer::lex;
+use zelkel_compiler::lint::unused;
+use zelkel_compiler::parser::parse;
+use zelkel_compiler::resolver::resolve;
+use zelkel_compiler::typechecker::check;
+use zelkel_compiler_fuzz::Program;
+
+// Generated programs are syntactically valid, so any lexer or parser error is a bug.
+// The later passes may still reject them, e.g. for a division by zero in a constant.
+fuzz_target!(|program: Program| {
+    let source = program.0;
+    let (tokens, errors) = lex(source.clone(), "fuzz.zk".to_string());
+    assert!(errors.is_empty(), "lexer rejected a generated program: {:?}\n{}", errors, source);
+    let (mut ast, errors) = parse(tokens);
+    assert!(errors.is_empty(), "parser rejected a generated program: {:?}\n{}", errors, source);
+
+    let Ok(mut symbols) = resolve(&mut ast, true) else {
+        return;
+    };
+    unused(&symbols);
+    if check(&mut ast, &mut symbols).is_ok() {
+        unreachable(&ast);
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zelkel_compiler::lexer::lex;
+
+fuzz_target!(|source: &str| {
+    lex(source.to_string(), "fuzz.zk".to_string());
+});
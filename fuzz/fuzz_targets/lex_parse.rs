@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zelkel_compiler::lexer::lex;
+use zelkel_compiler::parser::parse;
+
+fuzz_target!(|source: &str| {
+    let (tokens, _) = lex(source.to_string(), "fuzz.zk".to_string());
+    parse(tokens);
+});
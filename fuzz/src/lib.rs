@@ -0,0 +1,364 @@
+//! Grammar-aware input for the `generated` fuzz target. Programs are built by following the
+//! grammar rather than from raw bytes, so the fuzzer spends its time in the later passes
+//! instead of on input the lexer rejects straight away.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// Expressions deeper than this are cut off with a variable or literal.
+const MAX_EXPRESSION_DEPTH: usize = 4;
+const MAX_BLOCK_DEPTH: usize = 3;
+const MAX_STATEMENTS: usize = 6;
+const MAX_FUNCTIONS: usize = 4;
+const MAX_PARAMETERS: usize = 3;
+const MAX_CONSTANTS: usize = 2;
+
+/// A syntactically valid Zelkel program. Most programs type check as well, as names are
+/// only used in scope and with the type they were declared with.
+#[derive(Debug)]
+pub struct Program(pub String);
+
+impl<'a> Arbitrary<'a> for Program {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Program> {
+        let mut generator = Generator::default();
+        generator.program(u)?;
+        Ok(Program(generator.out))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Int,
+    Float,
+    Str,
+    Bool,
+}
+
+impl Type {
+    const ALL: [Type; 4] = [Type::Int, Type::Float, Type::Str, Type::Bool];
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Str => "str",
+            Type::Bool => "bool",
+        }
+    }
+}
+
+struct Function {
+    name: String,
+    params: Vec<Type>,
+    typ: Option<Type>,
+}
+
+#[derive(Default)]
+struct Generator {
+    out: String,
+    indent: usize,
+    functions: Vec<Function>,
+    /// Constants are visible everywhere, variables only in their scope
+    constants: Vec<(String, Type)>,
+    scopes: Vec<Vec<(String, Type)>>,
+    /// Return type of the function being generated
+    returns: Option<Type>,
+    /// String literals cannot appear inside an interpolation
+    interpolating: bool,
+    next_name: usize,
+}
+
+impl Generator {
+    fn fresh_name(&mut self, prefix: &str) -> String {
+        self.next_name += 1;
+        format!("{}{}", prefix, self.next_name)
+    }
+
+    fn line(&mut self, text: &str) {
+        self.out.push_str(&"    ".repeat(self.indent));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn names_of(&self, typ: Type) -> Vec<String> {
+        self.constants.iter()
+            .chain(self.scopes.iter().flatten())
+            .filter(|(_, t)| *t == typ)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Whether an expression of `typ` can be generated here.
+    fn can_make(&self, typ: Type) -> bool {
+        !(self.interpolating && typ == Type::Str && self.names_of(typ).is_empty())
+    }
+
+    fn program(&mut self, u: &mut Unstructured) -> Result<()> {
+        for _ in 0..u.int_in_range(0..=MAX_CONSTANTS)? {
+            let typ = *u.choose(&[Type::Int, Type::Float, Type::Str])?;
+            let name = self.fresh_name("C");
+            let value = self.literal(u, typ)?;
+            self.line(&format!("const {}: {} = {};", name, typ.name(), value));
+            self.constants.push((name, typ));
+        }
+
+        // Functions can be called before they are declared, so all signatures are picked up front
+        self.functions.push(Function { name: "main".to_string(), params: Vec::new(), typ: Some(Type::Int) });
+        for _ in 0..u.int_in_range(0..=MAX_FUNCTIONS)? {
+            let mut params = Vec::new();
+            for _ in 0..u.int_in_range(0..=MAX_PARAMETERS)? {
+                params.push(*u.choose(&Type::ALL)?);
+            }
+            let typ = if u.arbitrary()? { Some(*u.choose(&Type::ALL)?) } else { None };
+            let name = self.fresh_name("f");
+            self.functions.push(Function { name, params, typ });
+        }
+
+        for index in 0..self.functions.len() {
+            self.function(u, index)?;
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, u: &mut Unstructured, index: usize) -> Result<()> {
+        let public = index > 0 && u.arbitrary()?;
+        let (name, param_types, typ) = {
+            let function = &self.functions[index];
+            (function.name.clone(), function.params.clone(), function.typ)
+        };
+        let mut params = Vec::new();
+        let mut scope = Vec::new();
+        for typ in param_types {
+            let param = self.fresh_name("p");
+            params.push(format!("{}: {}", param, typ.name()));
+            scope.push((param, typ));
+        }
+        let returns = typ.map_or(String::new(), |typ| format!(" -> {}", typ.name()));
+        self.line(&format!("{}fn {}({}){} {{", if public { "pub " } else { "" }, name, params.join(", "), returns));
+
+        self.returns = typ;
+        self.scopes.push(scope);
+        self.indent += 1;
+        self.statements(u, 0)?;
+        if let Some(typ) = typ {
+            let value = self.expression(u, typ, 0)?;
+            self.line(&format!("return {};", value));
+        }
+        self.indent -= 1;
+        self.scopes.pop();
+        self.line("}");
+        Ok(())
+    }
+
+    fn statements(&mut self, u: &mut Unstructured, depth: usize) -> Result<()> {
+        for _ in 0..u.int_in_range(0..=MAX_STATEMENTS)? {
+            self.statement(u, depth)?;
+        }
+        Ok(())
+    }
+
+    /// A `{ ... }` body in a new scope, opened at the end of `header`.
+    fn body(&mut self, u: &mut Unstructured, header: &str, depth: usize) -> Result<()> {
+        self.line(&format!("{}{{", header));
+        self.scopes.push(Vec::new());
+        self.indent += 1;
+        self.statements(u, depth + 1)?;
+        self.indent -= 1;
+        self.scopes.pop();
+        self.line("}");
+        Ok(())
+    }
+
+    fn statement(&mut self, u: &mut Unstructured, depth: usize) -> Result<()> {
+        let nested = depth < MAX_BLOCK_DEPTH;
+        match u.int_in_range(0..=5)? {
+            1 => {
+                let call = self.call(u, None, 0)?;
+                self.line(&format!("{};", call));
+            }
+            2 if nested => self.body(u, "", depth)?,
+            3 if nested => {
+                let condition = self.expression(u, Type::Bool, 0)?;
+                self.body(u, &format!("if {} ", condition), depth)?;
+                if u.arbitrary()? {
+                    self.body(u, "else ", depth)?;
+                }
+            }
+            4 if nested => {
+                let condition = self.expression(u, Type::Bool, 0)?;
+                self.body(u, &format!("while {} ", condition), depth)?;
+            }
+            5 => match self.returns {
+                Some(typ) => {
+                    let value = self.expression(u, typ, 0)?;
+                    self.line(&format!("return {};", value));
+                }
+                None => self.line("return;"),
+            },
+            _ => {
+                let typ = *u.choose(&Type::ALL)?;
+                let value = self.expression(u, typ, 0)?;
+                let name = self.fresh_name("v");
+                // The type can be left out and inferred from the initialiser
+                if u.arbitrary()? {
+                    self.line(&format!("let {}: {} = {};", name, typ.name(), value));
+                } else {
+                    self.line(&format!("let {} = {};", name, value));
+                }
+                self.scopes.last_mut().unwrap().push((name, typ));
+            }
+        }
+        Ok(())
+    }
+
+    /// A call to any function returning `typ`, or to any function at all for `None`.
+    fn call(&mut self, u: &mut Unstructured, typ: Option<Type>, depth: usize) -> Result<String> {
+        let candidates: Vec<usize> = (0..self.functions.len())
+            .filter(|&index| typ.is_none() || self.functions[index].typ == typ)
+            .filter(|&index| self.functions[index].params.iter().all(|&param| self.can_make(param)))
+            .collect();
+        let index = *u.choose(&candidates)?;
+        let name = self.functions[index].name.clone();
+        let mut args = Vec::new();
+        for param in self.functions[index].params.clone() {
+            args.push(self.expression(u, param, depth + 1)?);
+        }
+        Ok(format!("{}({})", name, args.join(", ")))
+    }
+
+    fn has_call(&self, typ: Type) -> bool {
+        self.functions.iter().any(|f| f.typ == Some(typ) && f.params.iter().all(|&param| self.can_make(param)))
+    }
+
+    fn literal(&mut self, u: &mut Unstructured, typ: Type) -> Result<String> {
+        Ok(match typ {
+            Type::Int => {
+                let value = u.int_in_range(0..=i32::MAX)?;
+                match u.int_in_range(0..=4)? {
+                    0 => format!("{:#x}", value),
+                    1 => format!("{:#b}", value),
+                    2 => format!("{:#o}", value),
+                    // Digit separators, one every three digits from the right
+                    3 => {
+                        let digits = value.to_string();
+                        let groups: Vec<&str> = digits.as_bytes().rchunks(3).rev().map(|group| std::str::from_utf8(group).unwrap()).collect();
+                        groups.join("_")
+                    }
+                    _ => value.to_string(),
+                }
+            }
+            Type::Float if u.arbitrary()? => format!("{}.{}e{}", u.int_in_range(0..=9)?, u.int_in_range(0..=99)?, u.int_in_range(-30..=30)?),
+            Type::Float => format!("{}.{}", u.int_in_range(0..=9999)?, u.int_in_range(0..=99)?),
+            // Raw strings can contain quotes, backslashes and braces as they are
+            Type::Str if u.ratio(1, 4)? => {
+                let mut text = String::new();
+                for _ in 0..u.int_in_range(0..=8)? {
+                    text.push_str(u.choose(&["a", " ", "\"", "\\", "{", "}", "\n"])?);
+                }
+                format!("r#\"{}\"#", text)
+            }
+            Type::Str => format!("\"{}\"", self.text(u)?),
+            Type::Bool if u.arbitrary()? => u.choose(&["true", "false"])?.to_string(),
+            Type::Bool => format!("({} {} {})", u.int_in_range(0..=9)?, u.choose(&["==", "!=", "<", ">", "<=", ">="])?, u.int_in_range(0..=9)?),
+        })
+    }
+
+    /// The contents of a string literal, with escape sequences.
+    fn text(&mut self, u: &mut Unstructured) -> Result<String> {
+        let mut text = String::new();
+        for _ in 0..u.int_in_range(0..=8)? {
+            text.push_str(u.choose(&["a", "b", "z", "A", "0", "9", " ", "_", "é", "\\n", "\\t", "\\\"", "\\\\", "\\u{e9}", "{{", "}}"])?);
+        }
+        Ok(text)
+    }
+
+    /// A variable or literal of `typ`.
+    fn leaf(&mut self, u: &mut Unstructured, typ: Type) -> Result<String> {
+        let names = self.names_of(typ);
+        if !names.is_empty() && (self.interpolating && typ == Type::Str || u.arbitrary()?) {
+            return Ok(u.choose(&names)?.clone());
+        }
+        self.literal(u, typ)
+    }
+
+    fn expression(&mut self, u: &mut Unstructured, typ: Type, depth: usize) -> Result<String> {
+        if depth >= MAX_EXPRESSION_DEPTH || u.ratio(1, 3)? {
+            return self.leaf(u, typ);
+        }
+        if u.ratio(1, 5)? && self.has_call(typ) {
+            return self.call(u, Some(typ), depth);
+        }
+
+        let depth = depth + 1;
+        Ok(match typ {
+            Type::Int => match u.int_in_range(0..=3)? {
+                0 => format!("{}{}", u.choose(&["-", "~"])?, self.grouped(u, typ, depth)?),
+                1 => format!("{} as int", self.grouped(u, Type::Float, depth)?),
+                _ => {
+                    let op = u.choose(&["+", "-", "*", "/", "%", "&", "|", "^", "<<", ">>"])?;
+                    self.binary(u, typ, op, depth)?
+                }
+            },
+            Type::Float => match u.int_in_range(0..=3)? {
+                0 => format!("-{}", self.grouped(u, typ, depth)?),
+                1 => format!("{} as float", self.grouped(u, Type::Int, depth)?),
+                _ => {
+                    let op = u.choose(&["+", "-", "*", "/"])?;
+                    self.binary(u, typ, op, depth)?
+                }
+            },
+            Type::Bool => match u.int_in_range(0..=2)? {
+                0 => format!("!{}", self.grouped(u, typ, depth)?),
+                1 => {
+                    let op = u.choose(&["&&", "||"])?;
+                    self.binary(u, typ, op, depth)?
+                }
+                _ => {
+                    // Comparisons cannot be chained, so they are always parenthesised
+                    let operands = *u.choose(&[Type::Int, Type::Float])?;
+                    let op = u.choose(&["==", "!=", "<", ">", "<=", ">="])?;
+                    format!("({})", self.binary(u, operands, op, depth)?)
+                }
+            },
+            Type::Str if !self.interpolating => {
+                let mut text = String::from("\"");
+                for _ in 0..u.int_in_range(1..=3)? {
+                    text.push_str(&self.text(u)?);
+                    let part = *u.choose(&Type::ALL)?;
+                    if self.can_make_interpolated(part) {
+                        self.interpolating = true;
+                        let expr = self.expression(u, part, depth);
+                        self.interpolating = false;
+                        text.push_str(&format!("{{{}}}", expr?));
+                    }
+                }
+                text.push('"');
+                text
+            }
+            Type::Str => self.leaf(u, typ)?,
+        })
+    }
+
+    fn can_make_interpolated(&self, typ: Type) -> bool {
+        typ != Type::Str || !self.names_of(typ).is_empty()
+    }
+
+    fn binary(&mut self, u: &mut Unstructured, typ: Type, op: &str, depth: usize) -> Result<String> {
+        let left = self.operand(u, typ, depth)?;
+        let right = self.operand(u, typ, depth)?;
+        Ok(format!("{} {} {}", left, op, right))
+    }
+
+    /// An operand of a binary operator, parenthesised at random so both the precedence rules
+    /// and explicit grouping are exercised. Every operand has the type of the operator, so
+    /// leaving the parentheses out changes the tree but never its types.
+    fn operand(&mut self, u: &mut Unstructured, typ: Type, depth: usize) -> Result<String> {
+        let expr = self.expression(u, typ, depth)?;
+        Ok(if u.arbitrary()? { format!("({})", expr) } else { expr })
+    }
+
+    /// The operand of a prefix operator or cast, parenthesised unless it is a single term.
+    fn grouped(&mut self, u: &mut Unstructured, typ: Type, depth: usize) -> Result<String> {
+        let expr = self.expression(u, typ, depth)?;
+        Ok(if expr.contains(' ') { format!("({})", expr) } else { expr })
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+use crate::codes::UNREACHABLE_STATEMENT;
+use crate::diagnostic::{Diagnostic, Span};
+use crate::evaluator::evaluate;
+use crate::lexer::TokenValue;
+use crate::parser::{Expression, Statement, StatementKind};
+
+/// A statement in the control-flow graph, with edges to every statement that can run after it.
+#[derive(Debug)]
+pub struct Node<'a> {
+    /// `None` for the exit node, reached when the function returns
+    pub stmt: Option<&'a Statement>,
+    pub successors: Vec<usize>,
+}
+
+/// The control-flow graph of a function body, with a node per statement.
+#[derive(Debug)]
+pub struct ControlFlowGraph<'a> {
+    pub nodes: Vec<Node<'a>>,
+    pub entry: usize,
+    pub exit: usize,
+}
+
+/// The value of a condition if it is known at compile time.
+fn constant_condition(condition: &Expression) -> Option<bool> {
+    match evaluate(condition) {
+        Ok(TokenValue::Bool(value)) => Some(value),
+        _ => None,
+    }
+}
+
+impl<'a> ControlFlowGraph<'a> {
+    pub fn build(body: &'a [Statement]) -> ControlFlowGraph<'a> {
+        let mut cfg = ControlFlowGraph { nodes: vec![Node { stmt: None, successors: Vec::new() }], entry: 0, exit: 0 };
+        cfg.entry = cfg.add_body(body, cfg.exit);
+        cfg
+    }
+
+    fn add_node(&mut self, stmt: &'a Statement) -> usize {
+        self.nodes.push(Node { stmt: Some(stmt), successors: Vec::new() });
+        self.nodes.len() - 1
+    }
+
+    /// Adds the statements of `body`, which continues with `next` once it finishes.
+    /// Returns the node that is run first, which is `next` for an empty body.
+    fn add_body(&mut self, body: &'a [Statement], next: usize) -> usize {
+        body.iter().rev().fold(next, |next, stmt| self.add_statement(stmt, next))
+    }
+
+    fn add_statement(&mut self, stmt: &'a Statement, next: usize) -> usize {
+        let node = self.add_node(stmt);
+        self.nodes[node].successors = match &stmt.kind {
+            StatementKind::Return(_) => vec![self.exit],
+            StatementKind::Block(body) => vec![self.add_body(body, next)],
+            StatementKind::If(if_stmt) => {
+                let else_entry = match &if_stmt.else_body {
+                    Some(else_body) => self.add_body(else_body, next),
+                    None => next,
+                };
+                // Conditions are tested in order until one is true, the `else` runs if none is
+                let mut successors = Vec::new();
+                let mut taken = false;
+                for branch in &if_stmt.branches {
+                    let entry = self.add_body(&branch.body, next);
+                    match constant_condition(&branch.condition) {
+                        _ if taken => {}
+                        Some(true) => {
+                            successors.push(entry);
+                            taken = true;
+                        }
+                        Some(false) => {}
+                        None => successors.push(entry),
+                    }
+                }
+                if !taken {
+                    successors.push(else_entry);
+                }
+                successors
+            }
+            StatementKind::While(while_stmt) => {
+                // The body loops back to the condition
+                let body_entry = self.add_body(&while_stmt.body, node);
+                match constant_condition(&while_stmt.condition) {
+                    Some(true) => vec![body_entry],
+                    Some(false) => vec![next],
+                    None => vec![body_entry, next],
+                }
+            }
+            _ => vec![next],
+        };
+        node
+    }
+
+    /// The statements that can be reached from the start of the function.
+    pub fn reachable(&self) -> HashSet<*const Statement> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![self.entry];
+        let mut reachable = HashSet::new();
+        while let Some(node) = stack.pop() {
+            if std::mem::replace(&mut visited[node], true) {
+                continue;
+            }
+            if let Some(stmt) = self.nodes[node].stmt {
+                reachable.insert(stmt as *const Statement);
+            }
+            stack.extend(&self.nodes[node].successors);
+        }
+        reachable
+    }
+}
+
+/// Whether a `return` in `body`, including its nested bodies, can run.
+fn can_return(body: &[Statement], reachable: &HashSet<*const Statement>) -> bool {
+    body.iter().any(|stmt| reachable.contains(&(stmt as *const Statement)) && match &stmt.kind {
+        StatementKind::Return(_) => true,
+        StatementKind::Block(inner) => can_return(inner, reachable),
+        StatementKind::If(if_stmt) => if_stmt.branches.iter().any(|branch| can_return(&branch.body, reachable)) || if_stmt.else_body.as_ref().is_some_and(|body| can_return(body, reachable)),
+        StatementKind::While(while_stmt) => can_return(&while_stmt.body, reachable),
+        _ => false,
+    })
+}
+
+/// Why the statements following `stmt` cannot run, shown as a label on the warning.
+fn cause_after(stmt: &Statement, reachable: &HashSet<*const Statement>) -> (Span, String) {
+    let message = match &stmt.kind {
+        StatementKind::Return(_) => "any code following this return is unreachable",
+        // Only a loop whose condition is always true keeps the code following it from running
+        StatementKind::While(while_stmt) if can_return(&while_stmt.body, reachable) => "this loop only exits by returning from the function",
+        StatementKind::While(_) => "this loop never exits",
+        _ => "any code following this statement is unreachable",
+    };
+    (Span::new(stmt.pos.clone(), stmt.end.clone()), message.to_string())
+}
+
+fn always(condition: &Expression, value: bool) -> (Span, String) {
+    (condition.span(), format!("this condition is always {}", value))
+}
+
+/// Reports the first unreachable statement of `body` and of every reachable nested body.
+/// `cause` explains why the body itself would be unreachable.
+fn report_body(body: &[Statement], cause: (Span, String), reachable: &HashSet<*const Statement>, warnings: &mut Vec<Diagnostic>) {
+    for (index, stmt) in body.iter().enumerate() {
+        if !reachable.contains(&(stmt as *const Statement)) {
+            // Everything after an unreachable statement in the same body is unreachable too
+            let (span, message) = if index == 0 { cause } else { cause_after(&body[index - 1], reachable) };
+            warnings.push(Diagnostic::warning("Unreachable statement".to_string(), stmt.pos.clone())
+                .with_end(stmt.end.clone())
+                .with_code(UNREACHABLE_STATEMENT)
+                .with_label(span, message));
+            return;
+        }
+        match &stmt.kind {
+            StatementKind::Block(inner) => report_body(inner, cause_after(stmt, reachable), reachable, warnings),
+            StatementKind::If(if_stmt) => {
+                // A branch is skipped when its own condition is always false, or when an earlier one is always true
+                let mut taken: Option<&Expression> = None;
+                for branch in &if_stmt.branches {
+                    let cause = match taken {
+                        Some(condition) => always(condition, true),
+                        None => always(&branch.condition, false),
+                    };
+                    report_body(&branch.body, cause, reachable, warnings);
+                    if taken.is_none() && constant_condition(&branch.condition) == Some(true) {
+                        taken = Some(&branch.condition);
+                    }
+                }
+                if let Some(else_body) = &if_stmt.else_body {
+                    // Without an always true condition the `else` runs whenever the `if` does, so the cause isn't shown
+                    let condition = taken.unwrap_or(&if_stmt.branches[0].condition);
+                    report_body(else_body, always(condition, true), reachable, warnings);
+                }
+            }
+            StatementKind::While(while_stmt) => report_body(&while_stmt.body, always(&while_stmt.condition, false), reachable, warnings),
+            _ => {}
+        }
+    }
+}
+
+/// Warns about statements in function bodies that can never run, either because they follow
+/// a `return` or endless loop, or because they are in a branch whose condition is constant.
+pub fn unreachable(ast: &[Statement]) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    for stmt in ast {
+        if let StatementKind::FunctionDeclaration(function) = &stmt.kind {
+            let cfg = ControlFlowGraph::build(&function.body);
+            let reachable = cfg.reachable();
+            report_body(&function.body, cause_after(stmt, &reachable), &reachable, &mut warnings);
+        }
+    }
+    warnings
+}
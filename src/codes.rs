@@ -0,0 +1,362 @@
+//! Stable codes identifying every kind of diagnostic, and their longer explanations shown by `--explain`.
+//! Codes are never reused or renumbered, so they can be searched for and referred to in tools.
+//! Errors that can only be caused by a bug in the compiler itself have no code.
+
+pub const UNEXPECTED_CHARACTER: &str = "Z0001";
+pub const UNTERMINATED_STRING: &str = "Z0002";
+pub const UNTERMINATED_INTERPOLATION: &str = "Z0003";
+pub const STRING_IN_INTERPOLATION: &str = "Z0004";
+pub const EMPTY_INTERPOLATION: &str = "Z0005";
+pub const UNMATCHED_BRACE_IN_STRING: &str = "Z0006";
+pub const UNEXPECTED_END_OF_FILE: &str = "Z0007";
+pub const UNEXPECTED_TOKEN: &str = "Z0008";
+pub const INVALID_TYPE: &str = "Z0009";
+pub const EXPECTED_EXPRESSION: &str = "Z0010";
+pub const CHAINED_COMPARISON: &str = "Z0011";
+pub const NOT_AT_TOP_LEVEL: &str = "Z0012";
+pub const ELSE_WITHOUT_IF: &str = "Z0013";
+pub const ALREADY_DECLARED: &str = "Z0014";
+pub const UNKNOWN_IDENTIFIER: &str = "Z0015";
+pub const UNKNOWN_FUNCTION: &str = "Z0016";
+pub const INVALID_OPERANDS: &str = "Z0017";
+pub const INVALID_CAST: &str = "Z0018";
+pub const TYPE_MISMATCH: &str = "Z0019";
+pub const LOSSY_CONVERSION: &str = "Z0020";
+pub const WRONG_ARGUMENT_COUNT: &str = "Z0021";
+pub const NO_RETURN_VALUE: &str = "Z0022";
+pub const MISSING_RETURN_VALUE: &str = "Z0023";
+pub const RETURN_OUTSIDE_FUNCTION: &str = "Z0024";
+pub const NOT_CONSTANT: &str = "Z0025";
+pub const DIVISION_BY_ZERO: &str = "Z0026";
+pub const CONSTANT_OVERFLOW: &str = "Z0027";
+pub const INVALID_CONVERSION: &str = "Z0028";
+pub const SHADOWED_NAME: &str = "Z0029";
+pub const UNUSED_VARIABLE: &str = "Z0030";
+pub const UNUSED_FUNCTION: &str = "Z0031";
+pub const UNREACHABLE_STATEMENT: &str = "Z0032";
+pub const INTEGER_OUT_OF_RANGE: &str = "Z0033";
+pub const INVALID_NUMBER: &str = "Z0034";
+pub const NESTED_TOO_DEEPLY: &str = "Z0035";
+pub const UNSUPPORTED: &str = "Z0036";
+pub const UNTERMINATED_COMMENT: &str = "Z0037";
+pub const INVALID_ESCAPE: &str = "Z0038";
+
+pub struct Explanation {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    /// A minimal program producing the diagnostic
+    pub example: &'static str,
+    /// The same program with the problem fixed
+    pub fixed: &'static str,
+}
+
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: UNEXPECTED_CHARACTER,
+        title: "Unexpected character",
+        description: "The source contains a character that does not start any token, such as `$` or `@` outside of a string.",
+        example: "let price = 5$;",
+        fixed: "let price = 5;",
+    },
+    Explanation {
+        code: UNTERMINATED_STRING,
+        title: "Unterminated string",
+        description: "A string literal was opened with `\"` but the file ended before the closing `\"`. \
+            Strings can span several lines, so the error points at where the string started. \
+            A `\"` inside a string has to be escaped as `\\\"`.",
+        example: "let name = \"zelkel;",
+        fixed: "let name = \"zelkel\";",
+    },
+    Explanation {
+        code: UNTERMINATED_INTERPOLATION,
+        title: "Unterminated interpolation",
+        description: "An interpolation inside a string was opened with `{` but never closed with `}`.",
+        example: "let x = 1;\nlet message = \"x is {x",
+        fixed: "let x = 1;\nlet message = \"x is {x}\";",
+    },
+    Explanation {
+        code: STRING_IN_INTERPOLATION,
+        title: "String literal inside an interpolation",
+        description: "The expression inside `{...}` in a string cannot contain string literals, \
+            as the `\"` would end the outer string. Move the inner string to a variable first.",
+        example: "let message = \"{\"a\" + \"b\"}\";",
+        fixed: "let ab = \"a\" + \"b\";\nlet message = \"{ab}\";",
+    },
+    Explanation {
+        code: EMPTY_INTERPOLATION,
+        title: "Empty interpolation",
+        description: "A string contains `{}` with no expression inside. To include a literal brace, double it.",
+        example: "let braces = \"{}\";",
+        fixed: "let braces = \"{{}}\";",
+    },
+    Explanation {
+        code: UNMATCHED_BRACE_IN_STRING,
+        title: "Unmatched '}' in string",
+        description: "A string contains a `}` that does not close an interpolation. To include a literal brace, double it.",
+        example: "let brace = \"}\";",
+        fixed: "let brace = \"}}\";",
+    },
+    Explanation {
+        code: UNEXPECTED_END_OF_FILE,
+        title: "Unexpected end of file",
+        description: "The file ended in the middle of a statement or expression, usually because of a missing `;` or `}`.",
+        example: "let x = 1",
+        fixed: "let x = 1;",
+    },
+    Explanation {
+        code: UNEXPECTED_TOKEN,
+        title: "Unexpected token",
+        description: "The parser expected a specific token, such as `;` after a statement or `(` after a function name, \
+            but found something else.",
+        example: "let x = 1\nlet y = 2;",
+        fixed: "let x = 1;\nlet y = 2;",
+    },
+    Explanation {
+        code: INVALID_TYPE,
+        title: "Invalid type",
+        description: "A type annotation does not name one of the built in types: `int`, `float`, `str` or `bool`.",
+        example: "let x: integer = 1;",
+        fixed: "let x: int = 1;",
+    },
+    Explanation {
+        code: EXPECTED_EXPRESSION,
+        title: "Expected an expression",
+        description: "An expression was expected, for example after `=` or after a binary operator, \
+            but the next token cannot start one.",
+        example: "let x = 1 +;",
+        fixed: "let x = 1 + 2;",
+    },
+    Explanation {
+        code: CHAINED_COMPARISON,
+        title: "Chained comparison",
+        description: "Comparison operators cannot be chained, as `a < b < c` would compare the bool result of `a < b` with `c`. \
+            Combine separate comparisons with `&&` instead.",
+        example: "let x = 2;\nlet between = 1 < x < 3;",
+        fixed: "let x = 2;\nlet between = 1 < x && x < 3;",
+    },
+    Explanation {
+        code: NOT_AT_TOP_LEVEL,
+        title: "Declaration only allowed at the top level",
+        description: "Functions and constants can only be declared at the top level of a file, not inside a function body or block.",
+        example: "fn main() {\n    const LIMIT: int = 10;\n}",
+        fixed: "const LIMIT: int = 10;\nfn main() {\n}",
+    },
+    Explanation {
+        code: ELSE_WITHOUT_IF,
+        title: "'else' without a matching 'if'",
+        description: "An `else` must directly follow the closing `}` of an `if` body.",
+        example: "fn main(x: int) {\n    let y = x;\n    else {\n    }\n}",
+        fixed: "fn main(x: int) {\n    if x > 0 {\n        let y = x;\n    } else {\n    }\n}",
+    },
+    Explanation {
+        code: ALREADY_DECLARED,
+        title: "Name already declared",
+        description: "A variable, parameter, constant or function with the same name was already declared in the same scope. \
+            Names may shadow declarations from an enclosing block, but not be redeclared in the same one.",
+        example: "fn main() {\n    let x = 1;\n    let x = 2;\n}",
+        fixed: "fn main() {\n    let x = 1;\n    let y = 2;\n}",
+    },
+    Explanation {
+        code: UNKNOWN_IDENTIFIER,
+        title: "Unknown identifier",
+        description: "A name is used that is not declared in any enclosing scope. \
+            Variables are only visible after their declaration and until the end of the block they were declared in.",
+        example: "fn main() {\n    {\n        let x = 1;\n    }\n    let y = x;\n}",
+        fixed: "fn main() {\n    let x = 1;\n    let y = x;\n}",
+    },
+    Explanation {
+        code: UNKNOWN_FUNCTION,
+        title: "Unknown function",
+        description: "A function is called that is not declared anywhere in the file.",
+        example: "fn main() {\n    helper();\n}",
+        fixed: "fn helper() {\n}\nfn main() {\n    helper();\n}",
+    },
+    Explanation {
+        code: INVALID_OPERANDS,
+        title: "Invalid operand types",
+        description: "An operator is applied to values of types it does not support, for example `-` on a str \
+            or `&&` on ints. Arithmetic needs numbers, `+` also joins two strs, `&&`, `||` and `!` need bools, \
+            and bitwise operators need ints.",
+        example: "let x = \"a\" - \"b\";",
+        fixed: "let x = \"a\" + \"b\";",
+    },
+    Explanation {
+        code: INVALID_CAST,
+        title: "Invalid cast",
+        description: "There is no direct conversion between float and bool. Convert through int instead.",
+        example: "let x = 1.5 as bool;",
+        fixed: "let x = 1.5 as int as bool;",
+    },
+    Explanation {
+        code: TYPE_MISMATCH,
+        title: "Type mismatch",
+        description: "A value has a different type than the one expected, for example by a type annotation, \
+            a parameter or a condition. Only int is implicitly converted to float, use `as` for other conversions.",
+        example: "let x: int = \"5\";",
+        fixed: "let x: int = \"5\" as int;",
+    },
+    Explanation {
+        code: LOSSY_CONVERSION,
+        title: "Implicit conversion may lose precision",
+        description: "A float is used where an int is expected. This would drop the fractional part, \
+            so it has to be done explicitly with `as int`.",
+        example: "let x: int = 2.5;",
+        fixed: "let x: int = 2.5 as int;",
+    },
+    Explanation {
+        code: WRONG_ARGUMENT_COUNT,
+        title: "Wrong number of arguments",
+        description: "A function is called with a different number of arguments than it declares parameters.",
+        example: "fn add(a: int, b: int) -> int {\n    return a + b;\n}\nlet x = add(1);",
+        fixed: "fn add(a: int, b: int) -> int {\n    return a + b;\n}\nlet x = add(1, 2);",
+    },
+    Explanation {
+        code: NO_RETURN_VALUE,
+        title: "Function does not return a value",
+        description: "The result of a function without a return type is used as a value, \
+            or such a function returns a value. Declare the return type with `->`.",
+        example: "fn five() {\n    return 5;\n}",
+        fixed: "fn five() -> int {\n    return 5;\n}",
+    },
+    Explanation {
+        code: MISSING_RETURN_VALUE,
+        title: "Missing return value",
+        description: "A function declared with a return type uses `return` without a value.",
+        example: "fn five() -> int {\n    return;\n}",
+        fixed: "fn five() -> int {\n    return 5;\n}",
+    },
+    Explanation {
+        code: RETURN_OUTSIDE_FUNCTION,
+        title: "'return' outside of a function",
+        description: "`return` can only be used inside a function body.",
+        example: "let x = 1;\nreturn;",
+        fixed: "fn main() {\n    let x = 1;\n    return;\n}",
+    },
+    Explanation {
+        code: NOT_CONSTANT,
+        title: "Constant expression depends on a non-constant value",
+        description: "The value of a constant is computed while compiling, so it can only use literals, \
+            operators and other constants, not variables or function calls.",
+        example: "let base = 10;\nconst LIMIT: int = base * 2;",
+        fixed: "const BASE: int = 10;\nconst LIMIT: int = BASE * 2;",
+    },
+    Explanation {
+        code: DIVISION_BY_ZERO,
+        title: "Division by zero in a constant expression",
+        description: "A constant divides or takes the remainder by zero.",
+        example: "const HALF: int = 10 / 0;",
+        fixed: "const HALF: int = 10 / 2;",
+    },
+    Explanation {
+        code: CONSTANT_OVERFLOW,
+        title: "Overflow in a constant expression",
+        description: "Computing a constant produces an int outside the range of a 32-bit signed integer, \
+            or a float too large for a 32-bit float.",
+        example: "const BIG: int = 2147483647 + 1;",
+        fixed: "const BIG: float = 2147483647 as float + 1.0;",
+    },
+    Explanation {
+        code: INVALID_CONVERSION,
+        title: "Invalid conversion in a constant expression",
+        description: "A constant converts a str that does not contain a valid value of the target type.",
+        example: "const COUNT: int = \"ten\" as int;",
+        fixed: "const COUNT: int = \"10\" as int;",
+    },
+    Explanation {
+        code: SHADOWED_NAME,
+        title: "Declaration shadows a name",
+        description: "Reported with `--warn-shadowing`: a declaration hides one with the same name from an enclosing scope, \
+            which then cannot be used until the end of the block.",
+        example: "fn main() -> int {\n    let x = 1;\n    {\n        let x = 2;\n        return x;\n    }\n}",
+        fixed: "fn main() -> int {\n    let x = 1;\n    {\n        let y = 2;\n        return x + y;\n    }\n}",
+    },
+    Explanation {
+        code: UNUSED_VARIABLE,
+        title: "Unused variable",
+        description: "A variable or parameter is declared but never used. Prefix its name with `_` if this is intentional.",
+        example: "fn main() {\n    let unused = 1;\n}",
+        fixed: "fn main() {\n    let _unused = 1;\n}",
+    },
+    Explanation {
+        code: UNUSED_FUNCTION,
+        title: "Function is never called",
+        description: "A function other than `main` is never called. Declare it with `pub fn` if it is used from elsewhere, \
+            or prefix its name with `_` if this is intentional.",
+        example: "fn helper() {\n}\nfn main() {\n}",
+        fixed: "pub fn helper() {\n}\nfn main() {\n}",
+    },
+    Explanation {
+        code: UNREACHABLE_STATEMENT,
+        title: "Unreachable statement",
+        description: "A statement can never run, because it follows a `return` or an endless loop, \
+            or is in a branch whose condition is always false.",
+        example: "fn main() -> int {\n    return 1;\n    let x = 2;\n}",
+        fixed: "fn main() -> int {\n    let x = 2;\n    return x;\n}",
+    },
+    Explanation {
+        code: INTEGER_OUT_OF_RANGE,
+        title: "Integer literal out of range",
+        description: "An integer literal does not fit in an int, which is a 32-bit signed integer \
+            ranging from -2147483648 to 2147483647. Use a float literal for larger numbers.",
+        example: "let big = 3000000000;",
+        fixed: "let big = 3000000000.0;",
+    },
+    Explanation {
+        code: INVALID_NUMBER,
+        title: "Invalid number literal",
+        description: "A number literal is malformed, for example because it contains more than one decimal point, \
+            a digit that is invalid for its `0x`, `0b` or `0o` prefix, or a float that is out of range.",
+        example: "let version = 1.2.3;",
+        fixed: "let version = \"1.2.3\";",
+    },
+    Explanation {
+        code: NESTED_TOO_DEEPLY,
+        title: "Nested too deeply",
+        description: "Blocks are nested more than 64 levels deep, or an expression more than 32 levels, \
+            counting parentheses, prefix operators, right operands, calls and interpolations. A chain such as \
+            `1 + 1 + ... + 1` can be any length. Split the code up using variables or functions.",
+        example: "let x = ((((((((( ... (1) ... )))))))));",
+        fixed: "let inner = (((1)));\nlet x = ((((inner))));",
+    },
+    Explanation {
+        code: UNSUPPORTED,
+        title: "Not supported yet",
+        description: "The program uses a language feature that is planned but not implemented by the compiler yet, such as classes.",
+        example: "class Point {\n}",
+        fixed: "fn point_x() -> int {\n    return 0;\n}",
+    },
+    Explanation {
+        code: UNTERMINATED_COMMENT,
+        title: "Unterminated block comment",
+        description: "A `/*` block comment is not closed before the end of the file. Block comments nest, \
+            so every `/*` inside a comment needs its own `*/`.",
+        example: "/* outer /* inner */\nfn main() {}",
+        fixed: "/* outer /* inner */ */\nfn main() {}",
+    },
+    Explanation {
+        code: INVALID_ESCAPE,
+        title: "Invalid escape sequence",
+        description: "A backslash in a string starts an escape sequence, and only `\\n`, `\\r`, `\\t`, `\\0`, `\\\\`, `\\\"` \
+            and `\\u{...}` with the hexadecimal code of a Unicode character are supported. Use a raw string \
+            such as `r\"C:\\path\"` to write backslashes without escaping them.",
+        example: "let path = \"C:\\files\";",
+        fixed: "let path = r\"C:\\files\";",
+    },
+];
+
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS.iter().find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
+
+/// Indents every line of an example, so it stands out from the description.
+fn indent(example: &str) -> String {
+    example.lines().map(|line| format!("    {}", line)).collect::<Vec<_>>().join("\n")
+}
+
+impl Explanation {
+    pub fn render(&self) -> String {
+        format!("{}: {}\n\n{}\n\nErroneous example:\n\n{}\n\nFixed example:\n\n{}\n",
+            self.code, self.title, self.description, indent(self.example), indent(self.fixed))
+    }
+}
@@ -0,0 +1,341 @@
+use std::fmt;
+use crate::lexer::TokenPos;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A range of source text, `end` is the position just past the last character.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub start: TokenPos,
+    pub end: TokenPos,
+}
+
+impl Span {
+    pub fn new(start: TokenPos, end: TokenPos) -> Span {
+        Span { start, end }
+    }
+
+    /// A span covering the single character at `pos`.
+    pub fn at(pos: TokenPos) -> Span {
+        let mut end = pos.clone();
+        end.col += 1;
+        Span { start: pos, end }
+    }
+}
+
+/// Points at another location that helps explain the diagnostic, e.g. where a name was first declared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier of the kind of problem, e.g. `Z0001`
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, pos: TokenPos) -> Diagnostic {
+        Diagnostic::new(Severity::Error, message, pos)
+    }
+
+    pub fn warning(message: String, pos: TokenPos) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, message, pos)
+    }
+
+    fn new(severity: Severity, message: String, pos: TokenPos) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code: None,
+            message,
+            span: Span::at(pos),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Diagnostic {
+        self.code = Some(code);
+        self
+    }
+
+    /// Extends the primary span up to `end`.
+    pub fn with_end(mut self, end: TokenPos) -> Diagnostic {
+        if (end.line, end.col) > (self.span.start.line, self.span.start.col) {
+            self.span.end = end;
+        }
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: String) -> Diagnostic {
+        self.labels.push(Label { span, message });
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Picks colors for the parts of a rendered diagnostic, or nothing when color is disabled.
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, text: &str, color: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// One underline drawn below a source line.
+struct Marker<'a> {
+    start: usize,
+    end: usize,
+    primary: bool,
+    message: Option<&'a str>,
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic with the source lines it refers to, in the style of rustc:
+    ///
+    /// ```text
+    /// error: Variable 'a' already declared
+    ///  --> test.zk:3:5
+    ///   |
+    /// 2 |     let a: int = 1;
+    ///   |         - 'a' first declared here
+    /// 3 |     let a: int = 2;
+    ///   |         ^
+    /// ```
+    ///
+    /// `source` is the contents of the file the spans point into.
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let style = Style { color };
+        let severity_color = match self.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+        let marker_char = |primary: bool| if primary { '^' } else { '-' };
+        let marker_color = |primary: bool| if primary { severity_color } else { BLUE };
+
+        let mut out = match self.code {
+            Some(code) => style.paint(&format!("{}[{}]", self.severity, code), severity_color),
+            None => style.paint(&self.severity.to_string(), severity_color),
+        };
+        out.push_str(&style.paint(&format!(": {}", self.message), BOLD));
+        out.push('\n');
+
+        let lines: Vec<&str> = source.lines().collect();
+        let mut markers: Vec<(usize, Marker)> = Vec::new();
+        for (span, primary, message) in std::iter::once((&self.span, true, None))
+            .chain(self.labels.iter().map(|l| (&l.span, false, Some(l.message.as_str())))) {
+            let line = span.start.line;
+            if line == 0 || line > lines.len() || span.start.path != self.span.start.path {
+                continue;
+            }
+            // Spans across several lines are underlined up to the end of their first line
+            let line_len = lines[line - 1].chars().count();
+            let start = span.start.col.max(1);
+            let end = if span.end.line == line { span.end.col } else { line_len + 1 };
+            markers.push((line, Marker { start, end: end.max(start + 1), primary, message }));
+        }
+        markers.sort_by_key(|(line, marker)| (*line, marker.start));
+
+        let width = markers.iter().map(|(line, _)| line.to_string().len()).max().unwrap_or(1);
+        let gutter = style.paint(&format!("{} |", " ".repeat(width)), BLUE);
+        let start = &self.span.start;
+        out.push_str(&format!("{}{} {}:{}:{}\n", " ".repeat(width), style.paint("-->", BLUE), start.path, start.line, start.col));
+        if !markers.is_empty() {
+            out.push_str(&format!("{}\n", gutter));
+        }
+
+        let mut previous_line = None;
+        for (line, marker) in &markers {
+            if previous_line != Some(*line) {
+                if let Some(previous) = previous_line && *line > previous + 1 {
+                    out.push_str(&format!("{}\n", style.paint("...", BLUE)));
+                }
+                // Tabs are shown as a single space, so columns line up with the underline
+                let text = lines[line - 1].replace('\t', " ");
+                out.push_str(&format!("{} {}\n", style.paint(&format!("{:>width$} |", line, width = width), BLUE), text));
+                previous_line = Some(*line);
+            }
+            let underline = marker_char(marker.primary).to_string().repeat(marker.end - marker.start);
+            let mut annotation = style.paint(&underline, marker_color(marker.primary));
+            if let Some(message) = marker.message {
+                annotation.push(' ');
+                annotation.push_str(&style.paint(message, marker_color(marker.primary)));
+            }
+            out.push_str(&format!("{} {}{}\n", gutter, " ".repeat(marker.start - 1), annotation));
+        }
+
+        // Labels pointing into other files cannot be shown as a snippet
+        for label in self.labels.iter().filter(|l| l.span.start.path != self.span.start.path) {
+            let pos = &label.span.start;
+            out.push_str(&format!("{} {} {}:{}:{}: {}\n", " ".repeat(width), style.paint("-->", BLUE), pos.path, pos.line, pos.col, label.message));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} {} note: {}\n", " ".repeat(width), style.paint("=", BLUE), note));
+        }
+        out
+    }
+}
+
+/// Quotes and escapes `value` as a JSON string.
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_span(span: &Span) -> String {
+    format!("\"file\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}",
+        json_string(&span.start.path), span.start.line, span.start.col, span.end.line, span.end.col)
+}
+
+impl Diagnostic {
+    /// Serializes the diagnostic as a single line JSON object, for tools consuming compiler output:
+    ///
+    /// ```text
+    /// {"severity":"error","code":null,"message":"...","file":"test.zk","line":3,"column":5,
+    ///  "end_line":3,"end_column":6,"labels":[{"message":"...","file":...}],"notes":["..."]}
+    /// ```
+    pub fn to_json(&self) -> String {
+        let code = self.code.map_or("null".to_string(), json_string);
+        let labels: Vec<String> = self.labels.iter()
+            .map(|label| format!("{{\"message\":{},{}}}", json_string(&label.message), json_span(&label.span)))
+            .collect();
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+        format!("{{\"severity\":{},\"code\":{},\"message\":{},{},\"labels\":[{}],\"notes\":[{}]}}",
+            json_string(&self.severity.to_string()), code, json_string(&self.message), json_span(&self.span), labels.join(","), notes.join(","))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pos = &self.span.start;
+        match self.code {
+            Some(code) => write!(f, "{}[{}]: {}", self.severity, code, self.message)?,
+            None => write!(f, "{}: {}", self.severity, self.message)?,
+        }
+        write!(f, ", occurred near {}:{}:{}", pos.path, pos.line, pos.col)?;
+        for label in &self.labels {
+            let pos = &label.span.start;
+            write!(f, "; {} at {}:{}:{}", label.message, pos.path, pos.line, pos.col)?;
+        }
+        for note in &self.notes {
+            write!(f, "\n  = note: {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes every JSON string in `json` in order, keys included.
+    fn json_strings(json: &str) -> Vec<String> {
+        let mut strings = Vec::new();
+        let mut chars = json.chars();
+        while let Some(c) = chars.next() {
+            if c != '"' {
+                continue;
+            }
+            let mut value = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some('r') => value.push('\r'),
+                        Some('t') => value.push('\t'),
+                        Some('u') => {
+                            let hex: String = chars.by_ref().take(4).collect();
+                            value.push(char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap());
+                        }
+                        Some(c) => value.push(c),
+                        None => panic!("unterminated escape in {}", json),
+                    },
+                    c => value.push(c),
+                }
+            }
+            strings.push(value);
+        }
+        strings
+    }
+
+    fn pos(path: &str, line: usize, col: usize) -> TokenPos {
+        TokenPos { path: path.to_string(), line, col }
+    }
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string("a\"b\\c\nd\re\tf\u{1}g é"), r#""a\"b\\c\nd\re\tf\u0001g é""#);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let path = r"C:\src\main.zk";
+        let message = "Expected ';' but found \"a\\b\"\non two lines";
+        let label = "'x' was declared here:\n\t\"x\"";
+        let note = "use '\\' to escape a \"quote\"";
+        let diagnostic = Diagnostic::error(message.to_string(), pos(path, 3, 5))
+            .with_code("Z0008")
+            .with_end(pos(path, 3, 9))
+            .with_label(Span::new(pos(path, 1, 1), pos(path, 1, 2)), label.to_string())
+            .with_note(note.to_string());
+        let json = diagnostic.to_json();
+        assert!(!json.contains('\n'), "{}", json);
+        assert_eq!(json_strings(&json), [
+            "severity", "error", "code", "Z0008", "message", message, "file", path, "line", "column", "end_line", "end_column",
+            "labels", "message", label, "file", path, "line", "column", "end_line", "end_column", "notes", note,
+        ]);
+        assert!(json.contains(r#""line":3,"column":5,"end_line":3,"end_column":9,"labels":[{"#), "{}", json);
+        assert!(json.contains(r#""line":1,"column":1,"end_line":1,"end_column":2}],"notes":["#), "{}", json);
+    }
+}
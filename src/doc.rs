@@ -0,0 +1,287 @@
+use std::collections::HashSet;
+use crate::parser::{Parameter, Statement, StatementKind, ValueType};
+
+/// Output format of the `doc` command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocFormat {
+    Html,
+    Markdown,
+}
+
+impl DocFormat {
+    /// File extension of the generated pages.
+    pub fn extension(self) -> &'static str {
+        match self {
+            DocFormat::Html => "html",
+            DocFormat::Markdown => "md",
+        }
+    }
+}
+
+/// Name of the page listing the modules and built-in types, without extension.
+pub const INDEX: &str = "index";
+
+/// The built-in types, described on the index page so signatures can link to them.
+const TYPES: [(ValueType, &str); 4] = [
+    (ValueType::Integer, "A 32-bit signed integer, e.g. `42`, `1_000`, `0xff`, `0b1010` or `0o17`."),
+    (ValueType::Float, "A 32-bit floating point number, e.g. `1.5` or `2.5e-3`."),
+    (ValueType::String, "Text, which can contain `{expression}` interpolations and escapes such as `\\n`. Raw strings such as `r\"C:\\files\"` have neither."),
+    (ValueType::Bool, "Either `true` or `false`, e.g. the result of a comparison, used as the condition of `if` and `while`."),
+];
+
+/// A function or constant declared at the top level of a module.
+struct Item<'a> {
+    name: &'a str,
+    doc: Option<&'a str>,
+    kind: ItemKind<'a>,
+}
+
+enum ItemKind<'a> {
+    Constant(&'a ValueType),
+    Function {
+        public: bool,
+        params: &'a [Parameter],
+        typ: Option<&'a ValueType>,
+    },
+}
+
+fn items(ast: &[Statement]) -> Vec<Item<'_>> {
+    ast.iter().filter_map(|stmt| match &stmt.kind {
+        StatementKind::ConstantDeclaration(constant) => Some(Item {
+            name: &constant.name,
+            doc: constant.doc.as_deref(),
+            kind: ItemKind::Constant(&constant.typ),
+        }),
+        StatementKind::FunctionDeclaration(function) => Some(Item {
+            name: &function.name,
+            doc: function.doc.as_deref(),
+            kind: ItemKind::Function { public: function.public, params: &function.params, typ: function.typ.as_ref() },
+        }),
+        _ => None,
+    }).collect()
+}
+
+fn escape(text: &str, format: DocFormat) -> String {
+    match format {
+        DocFormat::Html => text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;"),
+        DocFormat::Markdown => text.to_string(),
+    }
+}
+
+fn link(text: &str, target: &str, format: DocFormat) -> String {
+    match format {
+        DocFormat::Html => format!("<a href=\"{}\">{}</a>", target, text),
+        DocFormat::Markdown => format!("[{}]({})", text, target),
+    }
+}
+
+fn code(text: &str, format: DocFormat) -> String {
+    match format {
+        DocFormat::Html => format!("<code>{}</code>", escape(text, format)),
+        DocFormat::Markdown => format!("`{}`", text),
+    }
+}
+
+/// The fragment linking to the heading of an item. Markdown renderers derive it from the
+/// heading text, in lowercase.
+fn anchor(name: &str, format: DocFormat) -> String {
+    match format {
+        DocFormat::Html => name.to_string(),
+        DocFormat::Markdown => name.to_lowercase(),
+    }
+}
+
+/// A type name linking to its description on the index page.
+fn type_link(typ: &ValueType, format: DocFormat) -> String {
+    let name = typ.to_string();
+    link(&code(&name, format), &format!("{}.{}#{}", INDEX, format.extension(), anchor(&name, format)), format)
+}
+
+/// Renders the text of a doc comment. Paragraphs are separated by blank lines and
+/// `code` spans naming an item of the module or a built-in type link to it.
+fn render_doc(text: &str, names: &HashSet<&str>, format: DocFormat) -> String {
+    let mut out = String::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        let mut inline = String::new();
+        let segments: Vec<&str> = paragraph.split('`').collect();
+        for (index, segment) in segments.iter().copied().enumerate() {
+            // An unclosed backtick is kept as text rather than starting a code span
+            let unclosed = index % 2 == 1 && index == segments.len() - 1;
+            if index % 2 == 0 || unclosed {
+                if unclosed {
+                    inline.push('`');
+                }
+                inline.push_str(&escape(segment, format));
+            } else if names.contains(segment) {
+                inline.push_str(&link(&code(segment, format), &format!("#{}", anchor(segment, format)), format));
+            } else if let Some((typ, _)) = TYPES.iter().find(|(typ, _)| typ.to_string() == segment) {
+                inline.push_str(&type_link(typ, format));
+            } else {
+                inline.push_str(&code(segment, format));
+            }
+        }
+        match format {
+            DocFormat::Html => out.push_str(&format!("<p>{}</p>\n", inline)),
+            DocFormat::Markdown => out.push_str(&format!("{}\n\n", inline)),
+        }
+    }
+    out
+}
+
+/// The declaration of an item, with its types linked, e.g. `pub fn add(a: int, b: int) -> int`.
+fn signature(item: &Item, format: DocFormat) -> String {
+    let name = match format {
+        DocFormat::Html => format!("<b>{}</b>", item.name),
+        DocFormat::Markdown => format!("**{}**", item.name),
+    };
+    match &item.kind {
+        ItemKind::Constant(typ) => format!("const {}: {}", name, type_link(typ, format)),
+        ItemKind::Function { public, params, typ } => {
+            let params: Vec<String> = params.iter()
+                .map(|param| format!("{}: {}", param.name, type_link(&param.typ, format)))
+                .collect();
+            let arrow = escape("->", format);
+            let returns = typ.map_or(String::new(), |typ| format!(" {} {}", arrow, type_link(typ, format)));
+            format!("{}fn {}({}){}", if *public { "pub " } else { "" }, name, params.join(", "), returns)
+        }
+    }
+}
+
+fn heading(level: usize, text: &str, id: Option<&str>, format: DocFormat) -> String {
+    match (format, id) {
+        (DocFormat::Html, Some(id)) => format!("<h{} id=\"{}\">{}</h{}>\n", level, id, text, level),
+        (DocFormat::Html, None) => format!("<h{}>{}</h{}>\n", level, text, level),
+        // Markdown renderers derive the anchor from the heading text
+        (DocFormat::Markdown, _) => format!("{} {}\n\n", "#".repeat(level), text),
+    }
+}
+
+fn page(title: &str, body: &str, format: DocFormat) -> String {
+    match format {
+        DocFormat::Html => format!("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+            <style>body {{ font-family: sans-serif; max-width: 50em; margin: auto; }} pre {{ background: #f4f4f4; padding: 0.5em; }}</style>\n\
+            </head>\n<body>\n{}</body>\n</html>\n", escape(title, format), body),
+        DocFormat::Markdown => body.to_string(),
+    }
+}
+
+/// Renders the documentation page of a module, listing its constants and functions in the
+/// order they are declared, with their signatures and doc comments.
+pub fn render_module(module: &str, ast: &[Statement], format: DocFormat) -> String {
+    let items = items(ast);
+    let names: HashSet<&str> = items.iter().map(|item| item.name).collect();
+    let mut body = match format {
+        DocFormat::Html => format!("<nav>{}</nav>\n", link("Index", &format!("{}.html", INDEX), format)),
+        DocFormat::Markdown => format!("{}\n\n", link("Index", &format!("{}.md", INDEX), format)),
+    };
+    body.push_str(&heading(1, &format!("Module {}", code(module, format)), None, format));
+
+    for (title, constants) in [("Constants", true), ("Functions", false)] {
+        let section: Vec<&Item> = items.iter().filter(|item| matches!(item.kind, ItemKind::Constant(_)) == constants).collect();
+        if section.is_empty() {
+            continue;
+        }
+        body.push_str(&heading(2, title, None, format));
+        for item in section {
+            body.push_str(&heading(3, item.name, Some(item.name), format));
+            match format {
+                DocFormat::Html => body.push_str(&format!("<pre>{}</pre>\n", signature(item, format))),
+                DocFormat::Markdown => body.push_str(&format!("{}\n\n", signature(item, format))),
+            }
+            if let Some(doc) = item.doc {
+                body.push_str(&render_doc(doc, &names, format));
+            }
+        }
+    }
+    page(&format!("Module {}", module), &body, format)
+}
+
+/// Renders the index page, linking to every module and describing the built-in types.
+pub fn render_index(modules: &[String], format: DocFormat) -> String {
+    let mut body = heading(1, "Documentation", None, format);
+    body.push_str(&heading(2, "Modules", None, format));
+    let links: Vec<String> = modules.iter()
+        .map(|module| link(&code(module, format), &format!("{}.{}", module, format.extension()), format))
+        .collect();
+    match format {
+        DocFormat::Html => body.push_str(&format!("<ul>\n{}</ul>\n", links.iter().map(|l| format!("<li>{}</li>\n", l)).collect::<String>())),
+        DocFormat::Markdown => body.push_str(&format!("{}\n\n", links.iter().map(|l| format!("- {}", l)).collect::<Vec<_>>().join("\n"))),
+    }
+
+    body.push_str(&heading(2, "Types", None, format));
+    let names = HashSet::new();
+    for (typ, description) in &TYPES {
+        let name = typ.to_string();
+        body.push_str(&heading(3, &name, Some(&name), format));
+        body.push_str(&render_doc(description, &names, format));
+    }
+    page("Documentation", &body, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+    use crate::parser::parse;
+
+    const SOURCE: &str = "/// The largest value, see `add` and `int`.\nconst MAX: int = 10;\n\n/// Adds `a` and <b> & more.\n///\n/// Never overflows.\npub fn add(a: int, b: int) -> int {\n    return a + b;\n}\n\nfn hidden() {\n}\n";
+
+    fn render(format: DocFormat) -> String {
+        let (toks, errors) = lex(SOURCE.to_string(), "test.zk".to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (ast, errors) = parse(toks);
+        assert!(errors.is_empty(), "{:?}", errors);
+        render_module("math", &ast, format)
+    }
+
+    #[test]
+    fn module_html() {
+        let html = render(DocFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>\n"), "{}", html);
+        for line in [
+            "<title>Module math</title>",
+            "<nav><a href=\"index.html\">Index</a></nav>",
+            "<h1>Module <code>math</code></h1>",
+            "<h2>Constants</h2>\n<h3 id=\"MAX\">MAX</h3>",
+            "<pre>const <b>MAX</b>: <a href=\"index.html#int\"><code>int</code></a></pre>",
+            "<p>The largest value, see <a href=\"#add\"><code>add</code></a> and <a href=\"index.html#int\"><code>int</code></a>.</p>",
+            "<pre>pub fn <b>add</b>(a: <a href=\"index.html#int\"><code>int</code></a>, b: <a href=\"index.html#int\"><code>int</code></a>) -&gt; <a href=\"index.html#int\"><code>int</code></a></pre>",
+            "<p>Adds <code>a</code> and &lt;b&gt; &amp; more.</p>\n<p>Never overflows.</p>",
+            "<h3 id=\"hidden\">hidden</h3>\n<pre>fn <b>hidden</b>()</pre>\n</body>",
+        ] {
+            assert!(html.contains(line), "missing {}\nin {}", line, html);
+        }
+    }
+
+    #[test]
+    fn module_markdown() {
+        assert_eq!(render(DocFormat::Markdown), "\
+[Index](index.md)
+
+# Module `math`
+
+## Constants
+
+### MAX
+
+const **MAX**: [`int`](index.md#int)
+
+The largest value, see [`add`](#add) and [`int`](index.md#int).
+
+## Functions
+
+### add
+
+pub fn **add**(a: [`int`](index.md#int), b: [`int`](index.md#int)) -> [`int`](index.md#int)
+
+Adds `a` and <b> & more.
+
+Never overflows.
+
+### hidden
+
+fn **hidden**()
+
+");
+    }
+}
@@ -0,0 +1,184 @@
+use crate::codes::{CONSTANT_OVERFLOW, DIVISION_BY_ZERO, INVALID_CONVERSION, NOT_CONSTANT};
+use crate::diagnostic::Diagnostic;
+use crate::error;
+use crate::lexer::{TokenPos, TokenValue};
+use crate::parser::{Expression, ExpressionKind, ValueType};
+
+fn integer_op(op: &str, left: i32, right: i32, pos: &TokenPos) -> Result<TokenValue, Diagnostic> {
+    let value = match op {
+        "+" => left.checked_add(right),
+        "-" => left.checked_sub(right),
+        "*" => left.checked_mul(right),
+        "/" | "%" if right == 0 => return Err(error(DIVISION_BY_ZERO, "Division by zero in constant expression".to_string(), pos.clone())),
+        "/" => left.checked_div(right),
+        "%" => left.checked_rem(right),
+        "==" => return Ok(TokenValue::Bool(left == right)),
+        "!=" => return Ok(TokenValue::Bool(left != right)),
+        "<" => return Ok(TokenValue::Bool(left < right)),
+        ">" => return Ok(TokenValue::Bool(left > right)),
+        "<=" => return Ok(TokenValue::Bool(left <= right)),
+        ">=" => return Ok(TokenValue::Bool(left >= right)),
+        "&" => Some(left & right),
+        "|" => Some(left | right),
+        "^" => Some(left ^ right),
+        "<<" => u32::try_from(right).ok().and_then(|r| left.checked_shl(r)),
+        ">>" => u32::try_from(right).ok().and_then(|r| left.checked_shr(r)),
+        _ => return Err(Diagnostic::error(format!("Operator '{}' is not supported on integers in constant expressions", op), pos.clone())),
+    };
+    match value {
+        Some(v) => Ok(TokenValue::Integer(v)),
+        None => Err(error(CONSTANT_OVERFLOW, format!("Integer overflow while evaluating '{} {} {}'", left, op, right), pos.clone())),
+    }
+}
+
+fn float_op(op: &str, left: f32, right: f32, pos: &TokenPos) -> Result<TokenValue, Diagnostic> {
+    let value = match op {
+        "+" => left + right,
+        "-" => left - right,
+        "*" => left * right,
+        "/" | "%" if right == 0.0 => return Err(error(DIVISION_BY_ZERO, "Division by zero in constant expression".to_string(), pos.clone())),
+        "/" => left / right,
+        "%" => left % right,
+        "==" => return Ok(TokenValue::Bool(left == right)),
+        "!=" => return Ok(TokenValue::Bool(left != right)),
+        "<" => return Ok(TokenValue::Bool(left < right)),
+        ">" => return Ok(TokenValue::Bool(left > right)),
+        "<=" => return Ok(TokenValue::Bool(left <= right)),
+        ">=" => return Ok(TokenValue::Bool(left >= right)),
+        _ => return Err(Diagnostic::error(format!("Operator '{}' is not supported on floats in constant expressions", op), pos.clone())),
+    };
+    // Literals are finite, so only an overflow can produce an infinity
+    if !value.is_finite() {
+        return Err(error(CONSTANT_OVERFLOW, format!("Float overflow while evaluating '{:?} {} {:?}'", left, op, right), pos.clone())
+            .with_note(format!("the largest float is {:e}", f32::MAX)));
+    }
+    Ok(TokenValue::Float(value))
+}
+
+fn string_op(op: &str, left: &str, right: &str, pos: &TokenPos) -> Result<TokenValue, Diagnostic> {
+    Ok(match op {
+        "+" => TokenValue::String(format!("{}{}", left, right)),
+        "==" => TokenValue::Bool(left == right),
+        "!=" => TokenValue::Bool(left != right),
+        "<" => TokenValue::Bool(left < right),
+        ">" => TokenValue::Bool(left > right),
+        "<=" => TokenValue::Bool(left <= right),
+        ">=" => TokenValue::Bool(left >= right),
+        _ => return Err(Diagnostic::error(format!("Operator '{}' is not supported on strings in constant expressions", op), pos.clone())),
+    })
+}
+
+fn evaluate_binary(op: &TokenValue, left: TokenValue, right: &Expression, pos: &TokenPos) -> Result<TokenValue, Diagnostic> {
+    let op = op.as_string();
+    let right = evaluate(right)?;
+    match (&left, &right) {
+        (TokenValue::Integer(l), TokenValue::Integer(r)) => integer_op(&op, *l, *r, pos),
+        (TokenValue::Float(l), TokenValue::Float(r)) => float_op(&op, *l, *r, pos),
+        (TokenValue::String(l), TokenValue::String(r)) => string_op(&op, l, r, pos),
+        (TokenValue::Bool(l), TokenValue::Bool(r)) if op == "==" => Ok(TokenValue::Bool(l == r)),
+        (TokenValue::Bool(l), TokenValue::Bool(r)) if op == "!=" => Ok(TokenValue::Bool(l != r)),
+        _ => Err(Diagnostic::error(format!("Cannot apply '{}' to {} and {} in a constant expression", op, left.describe(), right.describe()), pos.clone())),
+    }
+}
+
+fn cast(value: TokenValue, to: &ValueType, pos: &TokenPos) -> Result<TokenValue, Diagnostic> {
+    Ok(match (value, to) {
+        (TokenValue::Integer(v), ValueType::Float) => TokenValue::Float(v as f32),
+        // Truncates towards zero, saturating at the bounds of int
+        (TokenValue::Float(v), ValueType::Integer) => TokenValue::Integer(v as i32),
+        (TokenValue::Integer(v), ValueType::Bool) => TokenValue::Bool(v != 0),
+        (TokenValue::Bool(v), ValueType::Integer) => TokenValue::Integer(v as i32),
+        (TokenValue::String(v), ValueType::Integer) => TokenValue::Integer(v.trim().parse()
+            .map_err(|_| error(INVALID_CONVERSION, format!("Cannot convert \"{}\" to int", v), pos.clone()))?),
+        (TokenValue::String(v), ValueType::Float) => TokenValue::Float(v.trim().parse().ok().filter(|v: &f32| v.is_finite())
+            .ok_or_else(|| error(INVALID_CONVERSION, format!("Cannot convert \"{}\" to float", v), pos.clone()))?),
+        (TokenValue::String(v), ValueType::Bool) => TokenValue::Bool(v.trim().parse()
+            .map_err(|_| error(INVALID_CONVERSION, format!("Cannot convert \"{}\" to bool", v), pos.clone()))?),
+        (value, ValueType::String) => TokenValue::String(value.as_string()),
+        (value, _) => value,
+    })
+}
+
+/// Evaluates an expression at compile time, failing if it depends on anything that is not constant.
+pub fn evaluate(expr: &Expression) -> Result<TokenValue, Diagnostic> {
+    let chain = expr.chain();
+    let (first, operators) = chain.split_first().expect("the chain holds at least `expr`");
+    let mut value = evaluate_node(first, None)?;
+    for operator in operators {
+        value = evaluate_node(operator, Some(value))?;
+    }
+    Ok(value)
+}
+
+/// Evaluates `expr` given the value of its left operand, which is `None` unless it is an operator.
+fn evaluate_node(expr: &Expression, left: Option<TokenValue>) -> Result<TokenValue, Diagnostic> {
+    let pos = &expr.pos;
+    let left = || left.expect("left operands are evaluated by `evaluate`");
+    match &expr.kind {
+        ExpressionKind::Primary(primary) => Ok(primary.value.clone()),
+        ExpressionKind::Identifier(identifier) => {
+            Err(error(NOT_CONSTANT, format!("Constant expression cannot reference non-constant value '{}'", identifier.name), pos.clone()))
+        }
+        ExpressionKind::Call(call) => {
+            Err(error(NOT_CONSTANT, format!("Constant expression cannot call function '{}'", call.name), pos.clone()))
+        }
+        ExpressionKind::Unary(unary) => {
+            let value = evaluate(&unary.left)?;
+            match (unary.op.as_string().as_str(), &value) {
+                ("-", TokenValue::Integer(v)) => v.checked_neg()
+                    .map(TokenValue::Integer)
+                    .ok_or_else(|| error(CONSTANT_OVERFLOW, format!("Integer overflow while negating {}", v), pos.clone())),
+                ("-", TokenValue::Float(v)) => Ok(TokenValue::Float(-v)),
+                ("+", TokenValue::Integer(_) | TokenValue::Float(_)) => Ok(value),
+                ("!", TokenValue::Bool(v)) => Ok(TokenValue::Bool(!v)),
+                ("~", TokenValue::Integer(v)) => Ok(TokenValue::Integer(!v)),
+                (op, _) => Err(Diagnostic::error(format!("Cannot apply unary '{}' to {} in a constant expression", op, value.describe()), pos.clone())),
+            }
+        }
+        ExpressionKind::Term(term) => evaluate_binary(&term.op, left(), &term.right, pos),
+        ExpressionKind::Binary(binary) => evaluate_binary(&binary.op, left(), &binary.right, pos),
+        ExpressionKind::Comparison(comparison) => evaluate_binary(&comparison.op, left(), &comparison.right, pos),
+        ExpressionKind::Bitwise(bitwise) => evaluate_binary(&bitwise.op, left(), &bitwise.right, pos),
+        ExpressionKind::Cast(cast_expr) => cast(left(), &cast_expr.typ, pos),
+        ExpressionKind::Interpolation(interpolation) => {
+            let mut value = String::new();
+            for part in &interpolation.parts {
+                value.push_str(&evaluate(part)?.as_string());
+            }
+            Ok(TokenValue::String(value))
+        }
+        ExpressionKind::Logical(logical) => {
+            // Short-circuit: the right operand is never evaluated once the left one decides the result
+            let short_circuit = logical.op.as_string() == "||";
+            match left() {
+                TokenValue::Bool(left) if left == short_circuit => Ok(TokenValue::Bool(left)),
+                TokenValue::Bool(_) => match evaluate(&logical.right)? {
+                    TokenValue::Bool(right) => Ok(TokenValue::Bool(right)),
+                    right => Err(Diagnostic::error(format!("Cannot apply '{}' to {} in a constant expression", logical.op.as_string(), right.describe()), pos.clone())),
+                },
+                left => Err(Diagnostic::error(format!("Cannot apply '{}' to {} in a constant expression", logical.op.as_string(), left.describe()), pos.clone())),
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn float_code(op: &str, left: f32, right: f32) -> Option<&'static str> {
+        let pos = TokenPos { path: "test.zk".to_string(), line: 1, col: 1 };
+        float_op(op, left, right, &pos).err().and_then(|err| err.code)
+    }
+
+    #[test]
+    fn float_results_are_finite() {
+        assert_eq!(float_code("*", 1e38, 10.0), Some(CONSTANT_OVERFLOW));
+        assert_eq!(float_code("-", -3e38, 3e38), Some(CONSTANT_OVERFLOW));
+        assert_eq!(float_code("/", 1.0, 0.0), Some(DIVISION_BY_ZERO));
+        assert_eq!(float_code("%", 1.0, 0.0), Some(DIVISION_BY_ZERO));
+        assert_eq!(float_code("/", 0.0, -0.0), Some(DIVISION_BY_ZERO));
+        assert_eq!(float_code("+", 3e38, 3e37), None);
+    }
+}
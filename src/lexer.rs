@@ -1,19 +1,99 @@
-use crate::{error};
+use crate::codes::{EMPTY_INTERPOLATION, INTEGER_OUT_OF_RANGE, INVALID_ESCAPE, INVALID_NUMBER, STRING_IN_INTERPOLATION, UNEXPECTED_CHARACTER, UNMATCHED_BRACE_IN_STRING, UNTERMINATED_COMMENT, UNTERMINATED_INTERPOLATION, UNTERMINATED_STRING};
+use crate::diagnostic::Diagnostic;
+use crate::error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Literal(String),
+    Expression(Vec<Token>),
+}
+
+/// A word reserved by the language, which cannot be used as a name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Keyword {
+    As,
+    Class,
+    Const,
+    Else,
+    Fn,
+    If,
+    Let,
+    Pub,
+    Return,
+    While,
+}
+
+/// Every keyword, looked up when lexing a word.
+const KEYWORDS: [Keyword; 10] = [
+    Keyword::As,
+    Keyword::Class,
+    Keyword::Const,
+    Keyword::Else,
+    Keyword::Fn,
+    Keyword::If,
+    Keyword::Let,
+    Keyword::Pub,
+    Keyword::Return,
+    Keyword::While,
+];
+
+impl Keyword {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Keyword::As => "as",
+            Keyword::Class => "class",
+            Keyword::Const => "const",
+            Keyword::Else => "else",
+            Keyword::Fn => "fn",
+            Keyword::If => "if",
+            Keyword::Let => "let",
+            Keyword::Pub => "pub",
+            Keyword::Return => "return",
+            Keyword::While => "while",
+        }
+    }
+}
+
+/// The token for a word: a keyword, a boolean literal or otherwise an identifier.
+fn lex_word(word: &str) -> TokenValue {
+    match word {
+        "true" => TokenValue::Bool(true),
+        "false" => TokenValue::Bool(false),
+        _ => match KEYWORDS.iter().find(|keyword| keyword.as_str() == word) {
+            Some(keyword) => TokenValue::Keyword(*keyword),
+            None => TokenValue::Identifier(word.to_string()),
+        },
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenValue {
     Identifier(String),
+    Keyword(Keyword),
     String(String),
+    /// A string literal containing `{expression}` parts
+    InterpolatedString(Vec<StringPart>),
+    /// Literals are never negative, see [`TokenValue::IntegerMagnitude`].
     Integer(i32),
+    /// An integer literal that only fits in an int when negated, i.e. `2147483648`. The parser
+    /// rejects it anywhere but after a prefix `-`.
+    IntegerMagnitude(u32),
     Float(f32),
     Bool(bool),
     Arithmetic(String),
+    Logical(String),
+    Bitwise(String),
     Punctuation(String),
+    /// A `///` comment documenting the declaration that follows it
+    DocComment(String),
+    /// Input the lexer has already reported, e.g. an unexpected character or an unterminated
+    /// string. It stands in for the input so the parser doesn't report it a second time.
+    Error,
     Nested,
 }
 
 impl TokenValue {
-    pub fn empty(tok: &str) -> Result<TokenValue, String> {
+    pub fn empty(tok: &str) -> Result<TokenValue, Diagnostic> {
         match tok {
             "identifier" => Ok(TokenValue::Identifier("".to_owned())),
             "string" => Ok(TokenValue::String("".to_owned())),
@@ -21,188 +101,592 @@ impl TokenValue {
             "float" => Ok(TokenValue::Float(0.0)),
             "bool" => Ok(TokenValue::Bool(false)),
             "arithmetic" => Ok(TokenValue::Arithmetic("".to_owned())),
+            "logical" => Ok(TokenValue::Logical("".to_owned())),
+            "bitwise" => Ok(TokenValue::Bitwise("".to_owned())),
             "punctuation" => Ok(TokenValue::Punctuation("".to_owned())),
-            _ => Err(error(format!("Unknown token: {}", tok), TokenPos { path: "".to_string(), line: 0, col: 0 })),
+            _ => Err(Diagnostic::error(format!("Unknown token: {}", tok), TokenPos { path: "".to_string(), line: 0, col: 0 })),
         }
     }
 
     pub fn as_string(&self) -> String {
         match self {
             TokenValue::Identifier(s) => s.clone(),
+            TokenValue::Keyword(keyword) => keyword.as_str().to_string(),
             TokenValue::String(s) => s.clone(),
+            TokenValue::InterpolatedString(parts) => parts.iter().map(|part| match part {
+                StringPart::Literal(s) => s.clone(),
+                StringPart::Expression(toks) => format!("{{{}}}", toks.iter().map(|t| t.value.as_string()).collect::<Vec<_>>().join(" ")),
+            }).collect(),
             TokenValue::Integer(i) => i.to_string(),
+            TokenValue::IntegerMagnitude(magnitude) => magnitude.to_string(),
             TokenValue::Float(f) => f.to_string(),
             TokenValue::Bool(b) => b.to_string(),
             TokenValue::Arithmetic(s) => s.clone(),
+            TokenValue::Logical(s) => s.clone(),
+            TokenValue::Bitwise(s) => s.clone(),
             TokenValue::Punctuation(c) => c.to_string(),
+            TokenValue::DocComment(s) => s.clone(),
+            TokenValue::Error => String::new(),
             TokenValue::Nested => "nested".to_string(),
         }
     }
+
+    /// Describes the token in messages, e.g. `'('` or `a name`. An empty value, see
+    /// [`TokenValue::empty`], stands for any token of its kind.
+    pub fn describe(&self) -> String {
+        match self {
+            TokenValue::Identifier(s) if s.is_empty() => "a name".to_string(),
+            TokenValue::String(_) | TokenValue::InterpolatedString(_) => "a string".to_string(),
+            TokenValue::Arithmetic(s) | TokenValue::Logical(s) | TokenValue::Bitwise(s) if s.is_empty() => "an operator".to_string(),
+            TokenValue::Punctuation(s) if s.is_empty() => "punctuation".to_string(),
+            TokenValue::DocComment(_) => "a doc comment".to_string(),
+            TokenValue::Error => "invalid input".to_string(),
+            _ => format!("'{}'", self.as_string()),
+        }
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct TokenPos {
     pub path: String,
     pub line: usize,
     pub col: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub value: TokenValue,
     pub pos: TokenPos,
+    /// The position just past the last character of the token
+    pub end: TokenPos,
 }
 
 impl Token {
     pub fn empty() -> Token {
-        Token { value: TokenValue::Identifier("".to_owned()), pos: TokenPos { path: "".to_string(), line: 0, col: 0 } }
+        let pos = TokenPos { path: "".to_string(), line: 0, col: 0 };
+        Token { value: TokenValue::Identifier("".to_owned()), pos: pos.clone(), end: pos }
     }
 }
 
-fn could_be(c: char, s: &str) -> bool {
-    s.chars().any(|x| x == c)
+/// Walks the input one character at a time, keeping track of the position for the tokens.
+/// Lookahead only ever decodes the next character or two, so lexing stays linear in the input.
+struct Cursor<'a> {
+    input: &'a str,
+    /// Byte offset of the next character
+    offset: usize,
+    pos: TokenPos,
 }
 
-pub fn lex(input: String, path: String) -> Result<Vec<Token>, String> {
+impl<'a> Cursor<'a> {
+    fn new(input: &'a str, pos: TokenPos) -> Cursor<'a> {
+        Cursor { input, offset: 0, pos }
+    }
+
+    /// The input from `offset` up to the next character.
+    fn since(&self, offset: usize) -> &'a str {
+        &self.input[offset..self.offset]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// The character after the next one.
+    fn peek_second(&self) -> Option<char> {
+        self.rest().chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.col = 1;
+        } else {
+            self.pos.col += 1;
+        }
+        Some(c)
+    }
+
+    /// The input that has not been consumed yet.
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    /// Consumes the next character if it is `expected`.
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes characters while `predicate` holds, returning them.
+    fn eat_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.offset;
+        while self.peek().is_some_and(&predicate) {
+            self.bump();
+        }
+        &self.input[start..self.offset]
+    }
+}
+
+/// Splits the input into tokens. Lexing carries on after an error, skipping the offending
+/// characters, so the tokens can still be parsed and every error reported at once.
+pub fn lex(input: String, path: String) -> (Vec<Token>, Vec<Diagnostic>) {
+    let mut errors = Vec::new();
+    let toks = lex_from(&input, TokenPos { path, line: 1, col: 1 }, &mut errors);
+    (toks, errors)
+}
+
+/// Lexes `input` as if it started at `pos`, used for the expressions embedded in interpolated strings.
+fn lex_from(input: &str, pos: TokenPos, errors: &mut Vec<Diagnostic>) -> Vec<Token> {
+    let mut cursor = Cursor::new(input, pos);
     let mut toks: Vec<Token> = Vec::new();
-    let mut pos = TokenPos { path, line: 1, col: 1 };
-    let mut i = 0;
-
-    while i < input.len() {
-        let c = input.chars().nth(i).unwrap();
-        let mut token = Token { value: TokenValue::Identifier("".to_owned()), pos: pos.clone() };
-
-        if c.is_alphabetic() || c == '_' {
-            let mut value = String::new();
-            while i < input.len() && (input.chars().nth(i).unwrap().is_alphanumeric() || input.chars().nth(i).unwrap() == '_') {
-                value.push(input.chars().nth(i).unwrap());
-                i += 1;
-                pos.col += 1;
+
+    while let Some(c) = cursor.peek() {
+        let start = cursor.pos.clone();
+        let value = if c.is_whitespace() {
+            cursor.bump();
+            continue;
+        } else if c == '/' && cursor.peek_second() == Some('/') {
+            let comment = cursor.eat_while(|c| c != '\n');
+            // Four or more slashes make a plain comment again, e.g. a `////////` separator
+            match comment.strip_prefix("///") {
+                Some(text) if !text.starts_with('/') => TokenValue::DocComment(text.strip_prefix(' ').unwrap_or(text).trim_end().to_string()),
+                _ => continue,
             }
-            token.value = TokenValue::Identifier(value);
-        } else if c.is_digit(10) {
-            let mut value = String::new();
-            let mut is_float = false;
-            while i < input.len() && (input.chars().nth(i).unwrap().is_digit(10) || input.chars().nth(i).unwrap() == '.') {
-                if input.chars().nth(i).unwrap() == '.' {
-                    is_float = true;
-                }
-                value.push(input.chars().nth(i).unwrap());
-                i += 1;
-                pos.col += 1;
+        } else if c == '/' && cursor.peek_second() == Some('*') {
+            if skip_block_comment(&mut cursor, &start, errors) {
+                continue;
             }
-            token.value = if is_float {
-                TokenValue::Float(value.parse().unwrap())
-            } else {
-                TokenValue::Integer(value.parse().unwrap())
-            };
+            TokenValue::Error
+        } else if is_raw_string(cursor.rest()) {
+            lex_raw_string(&mut cursor, &start, errors)
+        } else if c.is_alphabetic() || c == '_' {
+            lex_word(cursor.eat_while(|c| c.is_alphanumeric() || c == '_'))
+        } else if c.is_ascii_digit() {
+            lex_number(&mut cursor, &start, errors)
         } else if c == '"' {
-            let mut value = String::new();
-            i += 1;
-            pos.col += 1;
-            while i < input.len() && input.chars().nth(i).unwrap() != '"' {
-                value.push(input.chars().nth(i).unwrap());
-                i += 1;
-                pos.col += 1;
+            lex_string(&mut cursor, &start, errors)
+        } else {
+            cursor.bump();
+            match c {
+                '+' | '*' | '/' | '%' => TokenValue::Arithmetic(c.to_string()),
+                '-' => if cursor.eat('>') {
+                    TokenValue::Punctuation("->".to_string())
+                } else {
+                    TokenValue::Arithmetic("-".to_string())
+                },
+                '>' => if cursor.eat('>') {
+                    TokenValue::Bitwise(">>".to_string())
+                } else if cursor.eat('=') {
+                    TokenValue::Arithmetic(">=".to_string())
+                } else {
+                    TokenValue::Arithmetic(">".to_string())
+                },
+                '<' => if cursor.eat('<') {
+                    TokenValue::Bitwise("<<".to_string())
+                } else if cursor.eat('=') {
+                    TokenValue::Arithmetic("<=".to_string())
+                } else {
+                    TokenValue::Arithmetic("<".to_string())
+                },
+                '!' => if cursor.eat('=') {
+                    TokenValue::Arithmetic("!=".to_string())
+                } else {
+                    TokenValue::Logical("!".to_string())
+                },
+                '&' => if cursor.eat('&') {
+                    TokenValue::Logical("&&".to_string())
+                } else {
+                    TokenValue::Bitwise("&".to_string())
+                },
+                '|' => if cursor.eat('|') {
+                    TokenValue::Logical("||".to_string())
+                } else {
+                    TokenValue::Bitwise("|".to_string())
+                },
+                '^' | '~' => TokenValue::Bitwise(c.to_string()),
+                '=' => if cursor.eat('=') {
+                    TokenValue::Arithmetic("==".to_string())
+                } else {
+                    TokenValue::Punctuation("=".to_string())
+                },
+                '(' | ')' | '{' | '}' | '[' | ']' | ',' | '.' | ';' | ':' => TokenValue::Punctuation(c.to_string()),
+                _ => {
+                    errors.push(error(UNEXPECTED_CHARACTER, format!("Unexpected character '{}'", c), start.clone()));
+                    TokenValue::Error
+                }
             }
-            if i < input.len() && input.chars().nth(i).unwrap() == '"' {
-                i += 1;
-                pos.col += 1;
-            } else {
-                return Err(error("Unterminated string".to_string(), pos));
+        };
+        toks.push(Token { value, pos: start, end: cursor.pos.clone() });
+    }
+
+    toks
+}
+
+/// Skips a `/* ... */` comment, which can contain nested block comments. Returns whether
+/// the comment was terminated.
+fn skip_block_comment(cursor: &mut Cursor, start: &TokenPos, errors: &mut Vec<Diagnostic>) -> bool {
+    cursor.bump();
+    cursor.bump();
+    let mut depth = 1;
+    while depth > 0 {
+        match cursor.bump() {
+            Some('/') if cursor.eat('*') => depth += 1,
+            Some('*') if cursor.eat('/') => depth -= 1,
+            Some(_) => {}
+            None => {
+                let mut opening = start.clone();
+                opening.col += 2;
+                errors.push(error(UNTERMINATED_COMMENT, "Unterminated block comment".to_string(), start.clone())
+                    .with_end(opening)
+                    .with_note("block comments nest, so every '/*' needs a matching '*/'".to_string()));
+                return false;
             }
+        }
+    }
+    true
+}
 
-            i += 1;
-            pos.col += 1;
-            token.value = TokenValue::String(value);
-        } else if could_be(c, "+*/%") {
-            token.value = TokenValue::Arithmetic(c.to_string());
-            i += 1;
-            pos.col += 1;
-        } else if c == '-' {
-            if i + 1 < input.len() && input.chars().nth(i + 1).unwrap() == '>' {
-                token.value = TokenValue::Punctuation("->".to_owned());
-                i += 2;
-                pos.col += 2;
-            } else {
-                token.value = TokenValue::Arithmetic("-".to_string());
-                i += 1;
-                pos.col += 1;
+/// Lexes a number literal: a decimal integer or float, which can have an exponent such as
+/// `1.5e-3`, or an integer with a `0x`, `0b` or `0o` prefix. Digits can be separated by `_`.
+fn lex_number(cursor: &mut Cursor, start: &TokenPos, errors: &mut Vec<Diagnostic>) -> TokenValue {
+    let begin = cursor.offset;
+    let base = match (cursor.peek(), cursor.peek_second()) {
+        (Some('0'), Some('x')) => Some((16, "hexadecimal")),
+        (Some('0'), Some('b')) => Some((2, "binary")),
+        (Some('0'), Some('o')) => Some((8, "octal")),
+        _ => None,
+    };
+    // A literal that fails to parse is still emitted, so it doesn't cause errors in the parser as well
+    if let Some((radix, name)) = base {
+        cursor.bump();
+        cursor.bump();
+        // Letters are consumed as well, so a digit that is invalid for the base is reported as part of the literal
+        let digits = cursor.eat_while(|c| c.is_ascii_alphanumeric() || c == '_').replace('_', "");
+        let literal = cursor.since(begin);
+        if let Some(invalid) = digits.chars().find(|c| !c.is_digit(radix)) {
+            errors.push(error(INVALID_NUMBER, format!("Invalid digit '{}' in {} literal '{}'", invalid, name, literal), start.clone())
+                .with_end(cursor.pos.clone()));
+            return TokenValue::Integer(0);
+        }
+        if digits.is_empty() {
+            errors.push(error(INVALID_NUMBER, format!("Missing digits in {} literal '{}'", name, literal), start.clone())
+                .with_end(cursor.pos.clone()));
+            return TokenValue::Integer(0);
+        }
+        return integer_value(u64::from_str_radix(&digits, radix).ok()).unwrap_or_else(|| {
+            errors.push(integer_out_of_range(literal, start, cursor));
+            TokenValue::Integer(0)
+        });
+    }
+
+    cursor.eat_while(|c| c.is_ascii_digit() || c == '_' || c == '.');
+    // `e` only starts an exponent if a digit or sign follows, so `2e` is still a number followed by a name
+    if matches!(cursor.peek(), Some('e' | 'E')) && cursor.peek_second().is_some_and(|c| c.is_ascii_digit() || c == '+' || c == '-') {
+        cursor.bump();
+        if !cursor.eat('+') {
+            cursor.eat('-');
+        }
+        cursor.eat_while(|c| c.is_ascii_digit() || c == '_');
+    }
+    let literal = cursor.since(begin);
+    let value = literal.replace('_', "");
+    if value.contains(['.', 'e', 'E']) {
+        match value.parse::<f32>() {
+            Ok(float) if float.is_finite() => TokenValue::Float(float),
+            Ok(_) => {
+                errors.push(error(INVALID_NUMBER, format!("Float literal '{}' is out of range for float", literal), start.clone())
+                    .with_end(cursor.pos.clone())
+                    .with_note(format!("the largest float is {:e}", f32::MAX)));
+                TokenValue::Float(0.0)
             }
-        } else if c == '>' {
-            if i + 1 < input.len() && input.chars().nth(i + 1).unwrap() == '=' {
-                token.value = TokenValue::Arithmetic(">=".to_string());
-                i += 2;
-                pos.col += 2;
-            } else {
-                token.value = TokenValue::Arithmetic(">".to_string());
-                i += 1;
-                pos.col += 1;
+            Err(_) => {
+                errors.push(error(INVALID_NUMBER, format!("Invalid number literal '{}'", literal), start.clone()).with_end(cursor.pos.clone()));
+                TokenValue::Float(0.0)
             }
-        } else if c == '<' {
-            if i + 1 < input.len() && input.chars().nth(i + 1).unwrap() == '=' {
-                token.value = TokenValue::Arithmetic("<=".to_string());
-                i += 2;
-                pos.col += 2;
-            } else {
-                token.value = TokenValue::Arithmetic("<".to_string());
-                i += 1;
-                pos.col += 1;
+        }
+    } else {
+        integer_value(value.parse().ok()).unwrap_or_else(|| {
+            errors.push(integer_out_of_range(literal, start, cursor));
+            TokenValue::Integer(0)
+        })
+    }
+}
+
+/// The token of an integer literal with the given magnitude, see [`TokenValue::IntegerMagnitude`].
+fn integer_value(magnitude: Option<u64>) -> Option<TokenValue> {
+    match magnitude? {
+        magnitude if magnitude == u64::from(i32::MIN.unsigned_abs()) => Some(TokenValue::IntegerMagnitude(i32::MIN.unsigned_abs())),
+        magnitude => i32::try_from(magnitude).ok().map(TokenValue::Integer),
+    }
+}
+
+fn integer_out_of_range(literal: &str, start: &TokenPos, cursor: &Cursor) -> Diagnostic {
+    error(INTEGER_OUT_OF_RANGE, format!("Integer literal '{}' is out of range for int", literal), start.clone())
+        .with_end(cursor.pos.clone())
+        .with_note(format!("the largest int is {}, use a float for larger numbers", i32::MAX))
+}
+
+/// Lexes an escape sequence such as `\n` or `\u{e9}` in a string, returning the character it
+/// stands for. An invalid escape is reported and left out of the string.
+fn lex_escape(cursor: &mut Cursor, errors: &mut Vec<Diagnostic>) -> Option<char> {
+    let start = cursor.pos.clone();
+    cursor.bump();
+    let escaped = match cursor.peek()? {
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        '0' => Some('\0'),
+        '\\' => Some('\\'),
+        '"' => Some('"'),
+        'u' => {
+            cursor.bump();
+            if !cursor.eat('{') {
+                errors.push(error(INVALID_ESCAPE, "Expected '{' after '\\u'".to_string(), start.clone())
+                    .with_end(cursor.pos.clone())
+                    .with_note("Unicode escapes are written as '\\u{...}' with 1 to 6 hexadecimal digits, e.g. '\\u{e9}'".to_string()));
+                return None;
             }
-        } else if c == '!' {
-            if i + 1 < input.len() && input.chars().nth(i + 1).unwrap() == '=' {
-                token.value = TokenValue::Arithmetic("!=".to_string());
-                i += 2;
-                pos.col += 2;
-            } else {
-                return Err(error("Unexpected character '!'".to_string(), pos));
+            // Letters are consumed as well, so a typo is reported as a single invalid escape
+            let digits = cursor.eat_while(|c| c.is_ascii_alphanumeric());
+            let closed = cursor.eat('}');
+            let escaped = u32::from_str_radix(digits, 16).ok().filter(|_| digits.len() <= 6).and_then(char::from_u32);
+            return match escaped {
+                Some(escaped) if closed => Some(escaped),
+                _ => {
+                    errors.push(error(INVALID_ESCAPE, format!("Invalid Unicode escape '\\u{{{}{}'", digits, if closed { "}" } else { "" }), start)
+                        .with_end(cursor.pos.clone())
+                        .with_note("Unicode escapes are written as '\\u{...}' with 1 to 6 hexadecimal digits, e.g. '\\u{e9}'".to_string()));
+                    None
+                }
+            };
+        }
+        other => {
+            cursor.bump();
+            errors.push(error(INVALID_ESCAPE, format!("Unknown escape sequence '\\{}'", other), start)
+                .with_end(cursor.pos.clone())
+                .with_note("the supported escapes are \\n, \\r, \\t, \\0, \\\\, \\\" and \\u{...}, or use a raw string such as r\"...\"".to_string()));
+            return None;
+        }
+    };
+    cursor.bump();
+    escaped
+}
+
+/// Whether `rest` starts with a raw string, `r"..."` or `r#"..."#` with any number of `#`.
+fn is_raw_string(rest: &str) -> bool {
+    rest.strip_prefix('r').is_some_and(|rest| rest.trim_start_matches('#').starts_with('"'))
+}
+
+/// Lexes a raw string, which has no escapes or interpolations and ends at a `"` followed by
+/// as many `#` as it was opened with, so it can contain quotes as in `r#"say "hi""#`.
+fn lex_raw_string(cursor: &mut Cursor, start: &TokenPos, errors: &mut Vec<Diagnostic>) -> TokenValue {
+    cursor.bump();
+    let hashes = cursor.eat_while(|c| c == '#').len();
+    cursor.bump();
+    let closing = format!("\"{}", "#".repeat(hashes));
+    let Some(len) = cursor.rest().find(&closing) else {
+        cursor.eat_while(|_| true);
+        errors.push(error(UNTERMINATED_STRING, "Unterminated raw string".to_string(), start.clone())
+            .with_end(cursor.pos.clone())
+            .with_note(format!("a raw string opened with 'r{}\"' ends at '{}'", "#".repeat(hashes), closing)));
+        return TokenValue::Error;
+    };
+    let value = &cursor.rest()[..len];
+    // Bumped one character at a time, so newlines in the string are counted
+    for _ in value.chars() {
+        cursor.bump();
+    }
+    for _ in closing.chars() {
+        cursor.bump();
+    }
+    TokenValue::String(value.to_string())
+}
+
+/// Lexes a string literal, splitting it into parts if it contains `{expression}` interpolations.
+fn lex_string(cursor: &mut Cursor, start: &TokenPos, errors: &mut Vec<Diagnostic>) -> TokenValue {
+    let mut parts: Vec<StringPart> = Vec::new();
+    let mut value = String::new();
+    // An unterminated string runs to the end of the file, so what it contains is most likely
+    // code rather than text and problems found in it are only reported once it is terminated
+    let mut inner: Vec<Diagnostic> = Vec::new();
+    cursor.bump();
+    while let Some(ch) = cursor.peek() {
+        if ch == '"' {
+            break;
+        } else if (ch == '{' || ch == '}') && cursor.peek_second() == Some(ch) {
+            value.push(ch);
+            cursor.bump();
+            cursor.bump();
+        } else if ch == '{' {
+            cursor.bump();
+            let interpolation = cursor.pos.clone();
+            let offset = cursor.offset;
+            let mut depth = 0;
+            while let Some(ch) = cursor.peek() {
+                if ch == '"' || (ch == '}' && depth == 0) {
+                    break;
+                } else if ch == '{' {
+                    depth += 1;
+                } else if ch == '}' {
+                    depth -= 1;
+                }
+                cursor.bump();
             }
-        } else if c == '&' {
-            if i + 1 < input.len() && input.chars().nth(i + 1).unwrap() == '&' {
-                token.value = TokenValue::Arithmetic("&&".to_string());
-                i += 2;
-                pos.col += 2;
-            } else {
-                return Err(error("Unexpected character '&'".to_string(), pos));
+            let source = &cursor.input[offset..cursor.offset];
+            match cursor.peek() {
+                None => {
+                    errors.push(error(UNTERMINATED_INTERPOLATION, "Unterminated interpolation".to_string(), interpolation).with_end(cursor.pos.clone()));
+                    return TokenValue::Error;
+                }
+                Some('"') => {
+                    // Most likely the closing quote of the string, so leave it to end the string
+                    inner.push(error(STRING_IN_INTERPOLATION, "String literals are not allowed inside an interpolation".to_string(), cursor.pos.clone()));
+                    continue;
+                }
+                Some(_) => cursor.bump(),
+            };
+            if source.trim().is_empty() {
+                inner.push(error(EMPTY_INTERPOLATION, "Empty interpolation, use '{{' for a literal brace".to_string(), interpolation).with_end(cursor.pos.clone()));
+                continue;
             }
-        } else if c == '|' {
-            if i + 1 < input.len() && input.chars().nth(i + 1).unwrap() == '|' {
-                token.value = TokenValue::Arithmetic("||".to_string());
-                i += 2;
-                pos.col += 2;
-            } else {
-                return Err(error("Unexpected character '|'".to_string(), pos));
+            if !value.is_empty() {
+                parts.push(StringPart::Literal(std::mem::take(&mut value)));
             }
-        } else if c == '=' {
-            if i + 1 < input.len() && input.chars().nth(i + 1).unwrap() == '=' {
-                token.value = TokenValue::Arithmetic("==".to_string());
-                i += 2;
-                pos.col += 2;
-            } else {
-                token.value = TokenValue::Punctuation("=".to_string());
-                i += 1;
-                pos.col += 1;
+            // Nothing in an interpolation can be documented, so doc comments are plain comments there
+            let mut part = lex_from(source, interpolation, &mut inner);
+            part.retain(|tok| !matches!(tok.value, TokenValue::DocComment(_)));
+            parts.push(StringPart::Expression(part));
+        } else if ch == '\\' {
+            if let Some(escaped) = lex_escape(cursor, &mut inner) {
+                value.push(escaped);
             }
-        } else if could_be(c, "(){}[],.;:") {
-            token.value = TokenValue::Punctuation(c.to_string());
-            i += 1;
-            pos.col += 1;
-        } else if c == '\n' {
-            pos.line += 1;
-            pos.col = 1;
-            i += 1;
-            pos.col += 1;
-            continue;
-        } else if c.is_whitespace() {
-            i += 1;
-            pos.col += 1;
-            continue;
+        } else if ch == '}' {
+            inner.push(error(UNMATCHED_BRACE_IN_STRING, "Unmatched '}' in string, use '}}' for a literal brace".to_string(), cursor.pos.clone()));
+            cursor.bump();
         } else {
-            return Err(error(format!("Unexpected character '{}'", c), pos));
+            value.push(ch);
+            cursor.bump();
+        }
+    }
+    if !cursor.eat('"') {
+        errors.push(error(UNTERMINATED_STRING, "Unterminated string".to_string(), start.clone()).with_end(cursor.pos.clone()));
+        return TokenValue::Error;
+    }
+    errors.append(&mut inner);
+
+    if parts.is_empty() {
+        TokenValue::String(value)
+    } else {
+        if !value.is_empty() {
+            parts.push(StringPart::Literal(value));
         }
+        TokenValue::InterpolatedString(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The values of the tokens lexed from `source`, which must lex without errors.
+    fn values(source: &str) -> Vec<TokenValue> {
+        let (toks, errors) = lex(source.to_string(), "test.zk".to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        toks.into_iter().map(|tok| tok.value).collect()
+    }
+
+    /// The line and column of every token lexed from `source`.
+    fn positions(source: &str) -> Vec<(usize, usize)> {
+        let (toks, errors) = lex(source.to_string(), "test.zk".to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        toks.iter().map(|tok| (tok.pos.line, tok.pos.col)).collect()
+    }
+
+    #[test]
+    fn positions_count_characters() {
+        let source = "let héllo = \"日本語\";\nlet 🎉 = \"🎉\" + ñ;";
+        let (toks, errors) = lex(source.to_string(), "test.zk".to_string());
+        assert_eq!(toks[1].value, TokenValue::Identifier("héllo".to_string()));
+        assert_eq!((toks[1].end.line, toks[1].end.col), (1, 10));
+        assert_eq!(toks[3].value, TokenValue::String("日本語".to_string()));
+        assert_eq!((toks[3].end.line, toks[3].end.col), (1, 18));
+        // The emoji is no identifier, but is reported at the character it starts at
+        assert_eq!((errors[0].span.start.line, errors[0].span.start.col), (2, 5));
+        assert_eq!(positions("let héllo = \"日本語\";\nlet x = \"🎉\" + ñ;"), [
+            (1, 1), (1, 5), (1, 11), (1, 13), (1, 18),
+            (2, 1), (2, 5), (2, 7), (2, 9), (2, 13), (2, 15), (2, 16),
+        ]);
+
+        let (toks, _) = lex("let s = \"ü {naïve}\";".to_string(), "test.zk".to_string());
+        let TokenValue::InterpolatedString(parts) = &toks[3].value else { panic!("expected an interpolation") };
+        let StringPart::Expression(inner) = &parts[1] else { panic!("expected an expression part") };
+        assert_eq!((inner[0].pos.col, inner[0].end.col), (13, 18));
+    }
 
-        toks.push(token);
+    #[test]
+    fn escapes() {
+        assert_eq!(values(r#""\u{e9}""#), [TokenValue::String("é".to_string())]);
+        assert_eq!(values(r#""\u{1F600}""#), [TokenValue::String("😀".to_string())]);
+        assert_eq!(values(r#""a\"b""#), [TokenValue::String("a\"b".to_string())]);
+        assert_eq!(values(r#""\\ \n\t\r\0""#), [TokenValue::String("\\ \n\t\r\0".to_string())]);
+        assert_eq!(values(r#""{{}}""#), [TokenValue::String("{}".to_string())]);
     }
 
-    Ok(toks)
-}
\ No newline at end of file
+    #[test]
+    fn raw_strings() {
+        assert_eq!(values(r##"r"a\nb""##), [TokenValue::String("a\\nb".to_string())]);
+        assert_eq!(values(r###"r#"say "hi" {x}"#"###), [TokenValue::String("say \"hi\" {x}".to_string())]);
+        assert_eq!(values(r###"r##"a "# b"##"###), [TokenValue::String("a \"# b".to_string())]);
+    }
+
+    #[test]
+    fn multi_line_strings() {
+        let (toks, errors) = lex("let s = \"one\ntwo\n\";\nlet t = r#\"three\nfour\"#;".to_string(), "test.zk".to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(toks[3].value, TokenValue::String("one\ntwo\n".to_string()));
+        assert_eq!((toks[4].pos.line, toks[4].pos.col), (3, 2));
+        assert_eq!((toks[5].pos.line, toks[5].pos.col), (4, 1));
+        assert_eq!(toks[8].value, TokenValue::String("three\nfour".to_string()));
+        assert_eq!((toks[9].pos.line, toks[9].pos.col), (5, 7));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(values("0xff 0b1010 0o17 1_000 0x_ff"), [255, 10, 15, 1000, 255].map(TokenValue::Integer));
+        assert_eq!(values("1.5e-3 2e3 1_0.2_5 0.5"), [1.5e-3, 2e3, 10.25, 0.5].map(TokenValue::Float));
+        assert_eq!(values("2147483647 0x7fffffff"), [i32::MAX, i32::MAX].map(TokenValue::Integer));
+    }
+
+    #[test]
+    fn smallest_int() {
+        let magnitude = TokenValue::IntegerMagnitude(2147483648);
+        assert_eq!(values("2147483648 0x80000000 0b1000_0000_0000_0000_0000_0000_0000_0000"), [magnitude.clone(), magnitude.clone(), magnitude]);
+        for literal in ["2147483649", "0x80000001", "99999999999999999999"] {
+            let (_, errors) = lex(literal.to_string(), "test.zk".to_string());
+            assert_eq!(errors.iter().map(|err| err.code).collect::<Vec<_>>(), [Some(INTEGER_OUT_OF_RANGE)], "{}", literal);
+        }
+    }
+
+    #[test]
+    fn comments_in_interpolation() {
+        for source in ["\"a {x // c} b\"", "\"a {x /// c} b\"", "\"a {x /* c */} b\""] {
+            let values = values(source);
+            let [TokenValue::InterpolatedString(parts)] = values.as_slice() else { panic!("expected an interpolation") };
+            let StringPart::Expression(inner) = &parts[1] else { panic!("expected an expression part") };
+            assert_eq!(inner.iter().map(|tok| tok.value.clone()).collect::<Vec<_>>(), [TokenValue::Identifier("x".to_string())], "{}", source);
+        }
+    }
+
+    #[test]
+    fn describe() {
+        let described: Vec<String> = values("fn f(\"a\") -> 2147483648").iter().map(TokenValue::describe).collect();
+        assert_eq!(described, ["'fn'", "'f'", "'('", "a string", "')'", "'->'", "'2147483648'"]);
+        assert_eq!(TokenValue::empty("identifier").unwrap().describe(), "a name");
+    }
+}
@@ -0,0 +1,25 @@
+// Diagnostics carry spans, labels and notes, and are only built on the error path
+#![allow(clippy::result_large_err)]
+
+use crate::diagnostic::{Diagnostic, Span};
+use crate::lexer::TokenPos;
+
+pub mod cfg;
+pub mod codes;
+pub mod diagnostic;
+pub mod doc;
+pub mod evaluator;
+pub mod lexer;
+pub mod lint;
+pub mod parser;
+pub mod resolver;
+pub mod typechecker;
+
+pub fn error(code: &'static str, message: String, pos: TokenPos) -> Diagnostic {
+    Diagnostic::error(message, pos).with_code(code)
+}
+
+/// An error underlining the whole of `span`, e.g. an expression.
+pub fn span_error(code: &'static str, message: String, span: Span) -> Diagnostic {
+    error(code, message, span.start).with_end(span.end)
+}
@@ -0,0 +1,44 @@
+use crate::codes::{UNUSED_FUNCTION, UNUSED_VARIABLE};
+use crate::diagnostic::Diagnostic;
+use crate::resolver::{SymbolKind, Symbols};
+
+/// The entry point of a program, which is called without being referenced.
+const ENTRY_POINT: &str = "main";
+
+/// Names starting with an underscore are deliberately unused.
+fn is_silenced(name: &str) -> bool {
+    name.starts_with('_')
+}
+
+/// Warns about variables, parameters and functions that are declared but never used.
+/// Public functions are exempt, as they can be called from outside the module.
+pub fn unused(symbols: &Symbols) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    for symbol in &symbols.symbols {
+        let what = match symbol.kind {
+            SymbolKind::Variable => "variable",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Constant => continue,
+        };
+        if symbol.used || is_silenced(&symbol.name) {
+            continue;
+        }
+        warnings.push(Diagnostic::warning(format!("Unused {}: '{}'", what, symbol.name), symbol.span.start.clone())
+            .with_end(symbol.span.end.clone())
+            .with_code(UNUSED_VARIABLE)
+            .with_note(format!("if this is intentional, prefix it with an underscore: '_{}'", symbol.name)));
+    }
+
+    for (name, function) in &symbols.functions {
+        if function.used || function.public || name == ENTRY_POINT || is_silenced(name) {
+            continue;
+        }
+        warnings.push(Diagnostic::warning(format!("Function '{}' is never called", name), function.span.start.clone())
+            .with_end(function.span.end.clone())
+            .with_code(UNUSED_FUNCTION)
+            .with_note(format!("declare it as 'pub fn {}' if it is used elsewhere, or prefix it with an underscore: '_{}'", name, name)));
+    }
+
+    warnings.sort_by_key(|warning| (warning.span.start.line, warning.span.start.col));
+    warnings
+}
@@ -1,30 +1,244 @@
-use crate::lexer::{lex, TokenPos};
-use crate::parser::parse;
+use std::io::IsTerminal;
+use zelkel_compiler::cfg::unreachable;
+use zelkel_compiler::codes::explain;
+use zelkel_compiler::diagnostic::{Diagnostic, Severity};
+use zelkel_compiler::doc::{render_index, render_module, DocFormat, INDEX};
+use zelkel_compiler::lexer::lex;
+use zelkel_compiler::lint::unused;
+use zelkel_compiler::parser::{parse, Statement};
+use zelkel_compiler::resolver::resolve;
+use zelkel_compiler::typechecker::check;
 
-mod lexer;
-mod parser;
+#[derive(PartialEq)]
+enum ErrorFormat {
+    /// Rendered with source snippets for people to read
+    Human,
+    /// One JSON object per line for editors and CI tools
+    Json,
+}
 
-pub fn error(message: String, pos: TokenPos) -> String {
-    format!("{}, occurred near {}:{}:{}", message, pos.path, pos.line, pos.col)
+#[derive(PartialEq)]
+enum Command {
+    /// Check a single file
+    Compile,
+    /// `doc`: generate documentation pages for every file given
+    Doc,
 }
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    let mut path: &String = &"test.zk".to_string();
-    if args.len() >= 2 {
-        path = &args[1];
+struct Options {
+    command: Command,
+    paths: Vec<String>,
+    /// `--warn-shadowing`: warn when a declaration shadows a name from an enclosing scope
+    warn_shadowing: bool,
+    /// `--max-errors=N`: stop reporting errors after the first `N`
+    max_errors: usize,
+    /// `--deny-warnings`: report warnings as errors and fail the compilation
+    deny_warnings: bool,
+    /// `--error-format=human|json`
+    error_format: ErrorFormat,
+    /// `--explain CODE`: describe an error code instead of compiling
+    explain: Option<String>,
+    /// `--format=html|markdown`: format of the pages written by `doc`
+    doc_format: DocFormat,
+    /// `--output=DIR`: directory the `doc` pages are written to
+    output: String,
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        command: Command::Compile,
+        paths: Vec::new(),
+        warn_shadowing: false,
+        max_errors: 20,
+        deny_warnings: false,
+        error_format: ErrorFormat::Human,
+        explain: None,
+        doc_format: DocFormat::Html,
+        output: "doc".to_string(),
+    };
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "doc").is_some() {
+        options.command = Command::Doc;
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--explain" => options.explain = Some(args.next().unwrap_or_else(|| {
+                eprintln!("--explain expects an error code, e.g. --explain Z0001");
+                std::process::exit(1);
+            })),
+            "--warn-shadowing" => options.warn_shadowing = true,
+            "--deny-warnings" => options.deny_warnings = true,
+            "--error-format=human" => options.error_format = ErrorFormat::Human,
+            "--error-format=json" => options.error_format = ErrorFormat::Json,
+            "--format=html" => options.doc_format = DocFormat::Html,
+            "--format=markdown" => options.doc_format = DocFormat::Markdown,
+            _ if arg.starts_with("--output=") => options.output = arg["--output=".len()..].to_string(),
+            _ if arg.starts_with("--max-errors=") => {
+                options.max_errors = arg["--max-errors=".len()..].parse().unwrap_or_else(|_| {
+                    eprintln!("Invalid value for --max-errors: {}", arg);
+                    std::process::exit(1);
+                });
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("Unknown option: {}", arg);
+                std::process::exit(1);
+            }
+            _ => options.paths.push(arg),
+        }
+    }
+    if options.paths.is_empty() {
+        options.paths.push("test.zk".to_string());
+    }
+    options
+}
+
+/// Prints a diagnostic to stderr. In the human format it is shown with a snippet of `source`,
+/// colored when stderr is a terminal.
+fn report(diagnostic: &Diagnostic, source: &str, options: &Options) {
+    match options.error_format {
+        ErrorFormat::Human => {
+            let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+            eprint!("{}", diagnostic.render(source, color));
+        }
+        ErrorFormat::Json => eprintln!("{}", diagnostic.to_json()),
+    }
+}
+
+/// Reports up to `--max-errors` errors, followed by how many there were.
+fn report_errors(errors: &[Diagnostic], source: &str, options: &Options) {
+    let max = options.max_errors;
+    for err in errors.iter().take(max) {
+        report(err, source, options);
+    }
+    if options.error_format == ErrorFormat::Json {
+        return;
+    }
+    if errors.len() > max {
+        eprintln!("error: aborting after {} errors, {} more not shown", max, errors.len() - max);
+    } else {
+        eprintln!("error: aborting due to {} previous error{}", errors.len(), if errors.len() == 1 { "" } else { "s" });
+    }
+    if let Some(code) = errors.iter().find_map(|err| err.code) {
+        eprintln!("For more information about an error, try '--explain {}'.", code);
+    }
+}
+
+/// Reports warnings, or fails the compilation on them with `--deny-warnings`.
+fn report_warnings(mut warnings: Vec<Diagnostic>, source: &str, options: &Options) {
+    if options.deny_warnings && !warnings.is_empty() {
+        for warning in &mut warnings {
+            warning.severity = Severity::Error;
+        }
+        report_errors(&warnings, source, options);
+        std::process::exit(1);
+    }
+    for warning in &warnings {
+        report(warning, source, options);
+    }
+}
+
+fn read(path: &str) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("error: cannot read '{}': {}", path, err);
+        std::process::exit(1);
+    })
+}
+
+/// Reads and parses a file, reporting every syntax error and exiting if there are any.
+fn read_and_parse(path: &str, options: &Options) -> (String, Vec<Statement>) {
+    let code = read(path);
+    // Parsing still runs after lexer errors, so syntax errors further on are reported as well
+    let (tokens, mut errors) = lex(code.clone(), path.to_string());
+    let (ast, parse_errors) = parse(tokens);
+    errors.extend(parse_errors);
+    errors.sort_by_key(|err| (err.span.start.line, err.span.start.col));
+    if !errors.is_empty() {
+        report_errors(&errors, &code, options);
+        std::process::exit(1);
     }
-    let code = std::fs::read_to_string(path).expect("Failed to read the file");
+    (code, ast)
+}
 
-    let tokens = lex(code, path.clone()).unwrap_or_else(|err| {
-        eprintln!("Compilation error: {}", err);
+fn write(path: &std::path::Path, contents: &str) {
+    std::fs::write(path, contents).unwrap_or_else(|err| {
+        eprintln!("error: cannot write '{}': {}", path.display(), err);
         std::process::exit(1);
     });
+}
 
-    let ast = parse(tokens).unwrap_or_else(|err| {
-        eprintln!("Compilation error: {}", err);
+/// Writes a documentation page for every file, named after the file, and an index linking to them.
+fn document(options: &Options) {
+    let output = std::path::Path::new(&options.output);
+    std::fs::create_dir_all(output).unwrap_or_else(|err| {
+        eprintln!("error: cannot create '{}': {}", output.display(), err);
         std::process::exit(1);
     });
+    let extension = options.doc_format.extension();
+    let mut modules = Vec::new();
+    for path in &options.paths {
+        let (_, ast) = read_and_parse(path, options);
+        let module = std::path::Path::new(path).file_stem().map_or(path.clone(), |stem| stem.to_string_lossy().into_owned());
+        if module == INDEX {
+            eprintln!("error: a module named '{}' would overwrite the index page", module);
+            std::process::exit(1);
+        }
+        write(&output.join(format!("{}.{}", module, extension)), &render_module(&module, &ast, options.doc_format));
+        modules.push(module);
+    }
+    let index = output.join(format!("{}.{}", INDEX, extension));
+    write(&index, &render_index(&modules, options.doc_format));
+    println!("Documented {} module{} in {}", modules.len(), if modules.len() == 1 { "" } else { "s" }, index.display());
+}
+
+/// The passes fit the 2 MiB a spawned thread gets by default within the nesting limits of the parser,
+/// but the main thread only has 1 MiB on some platforms.
+const STACK_SIZE: usize = 8 * 1024 * 1024;
 
-    println!("{:#?}", ast);
+fn main() {
+    let compiler = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("failed to spawn the compiler thread");
+    // A panic has already been printed by the thread
+    if compiler.join().is_err() {
+        std::process::exit(101);
+    }
+}
+
+fn run() {
+    let options = parse_args();
+    if let Some(code) = &options.explain {
+        match explain(code) {
+            Some(explanation) => print!("{}", explanation.render()),
+            None => {
+                eprintln!("error: '{}' is not a known error code", code);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if options.command == Command::Doc {
+        document(&options);
+        return;
+    }
+    if options.paths.len() > 1 {
+        eprintln!("error: expected a single file to compile, but got {}", options.paths.len());
+        std::process::exit(1);
+    }
+    let (code, mut ast) = read_and_parse(&options.paths[0], &options);
+
+    let mut symbols = resolve(&mut ast, options.warn_shadowing).unwrap_or_else(|err| {
+        report_errors(&[err], &code, &options);
+        std::process::exit(1);
+    });
+    let mut warnings = std::mem::take(&mut symbols.warnings);
+    warnings.extend(unused(&symbols));
+    report_warnings(warnings, &code, &options);
+
+    check(&mut ast, &mut symbols).unwrap_or_else(|err| {
+        report_errors(&[err], &code, &options);
+        std::process::exit(1);
+    });
+    // Runs after type checking, as constants have been inlined into conditions by then
+    report_warnings(unreachable(&ast), &code, &options);
 }
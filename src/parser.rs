@@ -1,536 +1,1119 @@
-use std::collections::HashMap;
-use crate::error;
-use crate::lexer::{Token, TokenPos, TokenValue};
-
-#[derive(Debug, Clone)]
-pub struct Statement {
-    kind: StatementKind,
-    pos: TokenPos,
-}
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum ValueType {
-    Integer,
-    Float,
-    String,
-    Bool,
-}
-
-#[derive(Debug, Clone)]
-pub enum StatementKind {
-    VariableDeclaration(VariableDeclaration),
-    FunctionDeclaration(FunctionDeclaration),
-    ExpressionStatement(ExpressionStatement),
-    Block(Vec<Statement>),
-}
-
-#[derive(Debug, Clone)]
-pub struct VariableDeclaration {
-    name: String,
-    typ: ValueType,
-    expr: Expression,
-}
-
-#[derive(Debug, Clone)]
-pub struct FunctionDeclaration {
-    name: String,
-    typ: Option<ValueType>,
-    body: Vec<Statement>,
-}
-
-#[derive(Debug, Clone)]
-pub struct ExpressionStatement {
-    typ: ValueType,
-    expr: Expression,
-}
-
-#[derive(Debug, Clone)]
-pub enum ExpressionKind {
-    Primary(PrimaryExpression),
-    Unary(Box<UnaryExpression>),
-    Term(Box<TermExpression>),
-    Binary(Box<BinaryExpression>),
-    Comparison(Box<ComparisonExpression>),
-}
-
-#[derive(Debug, Clone)]
-pub struct Expression {
-    kind: ExpressionKind,
-    typ: ValueType
-}
-
-#[derive(Debug, Clone)]
-pub struct PrimaryExpression {
-    value: TokenValue,
-    typ: ValueType,
-}
-
-#[derive(Debug, Clone)]
-pub struct UnaryExpression {
-    left: ExpressionKind,
-    typ: ValueType,
-    op: TokenValue,
-}
-
-#[derive(Debug, Clone)]
-pub struct TermExpression {
-    left: ExpressionKind,
-    right: ExpressionKind,
-    typ: ValueType,
-    op: TokenValue,
-}
-
-#[derive(Debug, Clone)]
-pub struct BinaryExpression {
-    left: ExpressionKind,
-    right: ExpressionKind,
-    typ: ValueType,
-    op: TokenValue,
-}
-
-#[derive(Debug, Clone)]
-pub struct ComparisonExpression {
-    left: ExpressionKind,
-    right: ExpressionKind,
-    typ: ValueType,
-    op: TokenValue,
-}
-
-#[derive(Debug, Clone)]
-pub struct VariableOptions {
-    pub mutable: bool,
-    pub typ: ValueType,
-}
-
-#[derive(Debug, Clone)]
-pub struct FunctionOptions {
-    pub args: Vec<VariableOptions>,
-    pub typ: Option<ValueType>,
-}
-
-#[derive(Clone, Debug)]
-pub struct Scope {
-    variables: HashMap<String, VariableOptions>,
-    functions: HashMap<String, FunctionOptions>,
-}
-
-fn expect(i: &usize, toks: &Vec<Token>, value: TokenValue) -> Result<Token, String> {
-    let mut i = *i;
-    if i >= toks.len() {
-        return Err(error("Unexpected end of file".to_string(), toks[i].pos.clone()));
-    }
-
-    if toks[i].value == value {
-        return Ok(toks[i].clone());
-    } else if let TokenValue::Identifier(_) | TokenValue::String(_) | TokenValue::Arithmetic(_) | TokenValue::Punctuation(_) = value {
-        return Ok(toks[i].clone());
-    }
-
-    Err(error(format!("Expected {:?} but got {:?}", value, toks[i].value), toks[i].pos.clone()))
-}
-
-fn enter_scope(scope: &mut Vec<Scope>) -> Vec<Scope> {
-    let parent_scope = scope.last().cloned().unwrap_or(Scope {
-        variables: HashMap::new(),
-        functions: HashMap::new(),
-    });
-    scope.push(parent_scope);
-    scope.clone()
-}
-
-fn exit_scope(scope: &mut Vec<Scope>) -> Vec<Scope> {
-    scope.pop();
-    scope.clone()
-}
-
-fn parse_type(tok: &Token) -> Result<ValueType, String> {
-    match tok.value {
-        TokenValue::Identifier(ref s) => match s.as_str() {
-            "int" => Ok(ValueType::Integer),
-            "str" => Ok(ValueType::String),
-            "float" => Ok(ValueType::Float),
-            "bool" => Ok(ValueType::Bool),
-            _ => Err(error(format!("Unknown type: '{}'", s), tok.pos.clone())),
-        },
-        _ => Err(error("Expected an identifier while parsing type".to_string(), tok.pos.clone())),
-    }
-}
-
-fn parse_primary_expression(i: &usize, toks: &Vec<Token>) -> Result<(Expression, usize), String> {
-    let mut i = *i;
-    let tok = &toks[i];
-    let expr = match &tok.value {
-        TokenValue::Integer(_) | TokenValue::Float(_) | TokenValue::String(_) | TokenValue::Bool(_) => {
-            Expression {
-                kind: ExpressionKind::Primary(PrimaryExpression {
-                    value: tok.value.clone(),
-                    typ: match &tok.value {
-                        TokenValue::Integer(_) => ValueType::Integer,
-                        TokenValue::Float(_) => ValueType::Float,
-                        TokenValue::String(_) => ValueType::String,
-                        TokenValue::Bool(_) => ValueType::Bool,
-                        _ => unreachable!(),
-                    },
-                }),
-                typ: match &tok.value {
-                    TokenValue::Integer(_) => ValueType::Integer,
-                    TokenValue::Float(_) => ValueType::Float,
-                    TokenValue::String(_) => ValueType::String,
-                    TokenValue::Bool(_) => ValueType::Bool,
-                    _ => unreachable!(),
-                },
-            }
-        }
-        TokenValue::Identifier(s) => {
-          todo!("parse identifiers as variables and such")
-        },
-        TokenValue::Punctuation(p) => {
-            if p == "(" {
-                i += 1;
-                let (expr, j) = parse_expression(&i, toks)?;
-                i = j;
-                expect(&i, &toks, TokenValue::Punctuation(")".to_string()))?;
-                expr
-            } else {
-                return Err(error("Expected a primary expression".to_string(), tok.pos.clone()));
-            }
-        }
-        _ => return Err(error("Expected a primary expression".to_string(), tok.pos.clone())),
-    };
-    i += 1;
-    Ok((expr, i))
-}
-
-fn parse_unary_expression(i: &usize, toks: &Vec<Token>) -> Result<(Expression, usize), String> {
-    let mut i = *i;
-    let tok = &toks[i];
-    if let TokenValue::Arithmetic(_) = &tok.value {
-        i += 1;
-        let (expr, j) = parse_primary_expression(&mut i, toks)?;
-        return Ok((Expression {
-            kind: ExpressionKind::Unary(Box::from(UnaryExpression {
-                left: expr.kind,
-                typ: expr.typ.clone(),
-                op: tok.clone().value,
-            })),
-            typ: expr.typ,
-        }, j));
-    }
-    parse_primary_expression(&mut i, toks)
-}
-
-fn parse_term_expression(i: &usize, toks: &Vec<Token>) -> Result<(Expression, usize), String> {
-    let mut i = *i;
-    let (mut expr, j) = parse_unary_expression(&mut i, toks)?;
-    i = j;
-    while i < toks.len() {
-        let tok = &toks[i];
-        if let TokenValue::Arithmetic(op) = &tok.value {
-            if op == "*" || op == "/" {
-                i += 1;
-                let (right, h) = parse_unary_expression(&mut i, toks)?;
-                i = h;
-                expr = Expression {
-                    kind: ExpressionKind::Term(Box::from(TermExpression {
-                        left: expr.kind,
-                        right: right.kind,
-                        typ: expr.typ.clone(),
-                        op: tok.clone().value,
-                    })),
-                    typ: expr.typ,
-                };
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-    Ok((expr, i))
-}
-
-fn parse_binary_expression(i: &usize, toks: &Vec<Token>) -> Result<(Expression, usize), String> {
-    let mut i = *i;
-    let (mut expr, j) = parse_term_expression(&mut i, toks)?;
-    i = j;
-    while i < toks.len() {
-        let tok = &toks[i];
-        if let TokenValue::Arithmetic(op) = &tok.value {
-            if op == "+" || op == "-" || op == "%" {
-                i += 1;
-                let (right, h) = parse_term_expression(&mut i, toks)?;
-                i = h;
-                expr = Expression {
-                    kind: ExpressionKind::Binary(Box::from(BinaryExpression {
-                        left: expr.kind,
-                        right: right.kind,
-                        typ: expr.typ.clone(),
-                        op: tok.clone().value,
-                    })),
-                    typ: expr.typ,
-                };
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-    Ok((expr, i))
-}
-
-fn parse_comparison_expression(i: &usize, toks: &Vec<Token>) -> Result<(Expression, usize), String> {
-    let mut i = *i;
-    let (mut expr, j) = parse_binary_expression(&mut i, toks)?;
-    i = j;
-    while i < toks.len() {
-        let tok = &toks[i];
-        if let TokenValue::Arithmetic(op) = &tok.value {
-            if op == "==" || op == "!=" || op == "<" || op == ">" || op == "<=" || op == ">=" {
-                i += 1;
-                let (right, h) = parse_binary_expression(&mut i, toks)?;
-                i = h;
-                expr = Expression {
-                    kind: ExpressionKind::Comparison(Box::from(ComparisonExpression {
-                        left: expr.kind,
-                        right: right.kind,
-                        typ: expr.typ.clone(),
-                        op: tok.clone().value,
-                    })),
-                    typ: expr.typ,
-                };
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-    Ok((expr, i))
-}
-
-fn parse_expression(i: &usize, toks: &Vec<Token>) -> Result<(Expression, usize), String> {
-    parse_comparison_expression(i, toks)
-}
-
-fn parse_body(i: &usize, toks: &Vec<Token>, global_scope: &mut Vec<Scope>) -> Result<(Vec<Statement>, usize), String> {
-    let mut i = *i;
-    let mut body: Vec<Statement> = Vec::new();
-    while i < toks.len() {
-        let tok = &toks[i];
-        if let TokenValue::Punctuation(p) = &tok.value {
-            if p == "}" {
-                break;
-            } else if p == "{" {
-                i += 1;
-                let (nested_body, j) = parse_body(&i, toks, global_scope)?;
-                i = j;
-                body.push(Statement {
-                    kind: StatementKind::Block(nested_body),
-                    pos: tok.pos.clone(),
-                });
-                continue;
-            }
-        }
-        let (stmt, j, _) = parse_statement(&i, &toks, global_scope)?;
-        i = j;
-        body.push(stmt);
-    }
-    Ok((body, i))
-}
-
-fn parse_class_declaration(i: &usize, toks: &Vec<Token>) -> Result<(Statement, usize), String> {
-    let mut i = *i;
-    i += 1;
-    let name = expect(&i, &toks, TokenValue::empty("identifier")?)?.value.as_string();
-    i += 1;
-    expect(&i, &toks, TokenValue::Punctuation("{".to_string()))?;
-    i += 1;
-    todo!("parse class body, should only be function declarations");
-    expect(&i, &toks, TokenValue::Punctuation("}".to_string()))?;
-    i += 1;
-    todo!("do rest");
-}
-
-fn parse_declaration_arguments(i: &usize, toks: &Vec<Token>) -> Result<(Vec<VariableOptions>, usize), String> {
-    let mut i = *i;
-    let mut args: Vec<VariableOptions> = Vec::new();
-    while i < toks.len() {
-        let tok = &toks[i];
-        if let TokenValue::Identifier(_) = &tok.value {
-            i += 1;
-            expect(&i, &toks, TokenValue::Punctuation(":".to_string()))?;
-            i += 1;
-            let type_ident = expect(&i, &toks, TokenValue::empty("identifier")?)?;
-            let typ = parse_type(&type_ident)?;
-            i += 1;
-            args.push(VariableOptions {
-                mutable: false,
-                typ,
-            });
-            if let TokenValue::Punctuation(p) = &toks[i].value {
-                if p == "," {
-                    i += 1;
-                } else {
-                    break;
-                }
-            } else {
-                break;
-            }
-        } else {
-            break;
-        }
-    }
-    Ok((args, i))
-}
-
-fn parse_function_declaration(i: &usize, toks: &Vec<Token>, global_scope: &mut Vec<Scope>) -> Result<(Statement, usize, Vec<Scope>), String> {
-    let mut i = *i;
-    i += 1;
-    let name = expect(&i, &toks, TokenValue::empty("identifier")?)?.value.as_string();
-    i += 1;
-    expect(&i, &toks, TokenValue::Punctuation("(".to_string()))?;
-    let (args, j) = parse_declaration_arguments(&i, &toks)?;
-    i += 1;
-    expect(&i, &toks, TokenValue::Punctuation(")".to_string()))?;
-    i += 1;
-    let typ: Option<ValueType>;
-    if let Ok(a) = expect(&i, &toks, TokenValue::Punctuation("->".to_string())) {
-        if a.value != TokenValue::Punctuation("->".to_string()) {
-            typ = None
-        } else {
-            i += 1;
-            typ = Some(parse_type(&expect(&i, &toks, TokenValue::empty("identifier")?)?)?);
-            i += 1;
-        }
-    } else {
-        typ = None;
-    }
-    expect(&i, &toks, TokenValue::Punctuation("{".to_string()))?;
-    let mut scope = enter_scope(global_scope);
-    i += 1;
-    let (body, j) = parse_body(&i, &toks, global_scope)?;
-    i = j;
-    expect(&i, &toks, TokenValue::Punctuation("}".to_string()))?;
-    i += 1;
-    scope = exit_scope(&mut scope);
-
-    scope.last_mut().unwrap().functions.insert(name.clone(), FunctionOptions {
-        args,
-        typ: typ.clone(),
-    });
-
-    Ok((Statement {
-        kind: StatementKind::FunctionDeclaration(FunctionDeclaration {
-            name,
-            typ,
-            body,
-        }),
-        pos: toks[i - 1].pos.clone(),
-    }, i, scope.clone()))
-}
-
-fn parse_variable_declaration(i: &usize, toks: &Vec<Token>, global_scope: &mut Vec<Scope>) -> Result<(Statement, usize, Vec<Scope>), String> {
-    let mut i = *i;
-    let mut global_scope = global_scope.clone();
-    i += 1;
-    let name = expect(&i, &toks, TokenValue::empty("identifier")?)?.value.as_string();
-
-    if global_scope.last().unwrap().variables.iter().any(|v| v.0 == &name) {
-        return Err(error(format!("Variable '{}' already declared", name), toks[i].pos.clone()));
-    }
-
-    i += 1;
-    expect(&i, &toks, TokenValue::Punctuation(":".to_string()))?;
-    i += 1;
-    let type_ident = expect(&i, &toks, TokenValue::empty("identifier")?)?;
-    let typ = parse_type(&type_ident)?;
-    i += 1;
-    expect(&i, &toks, TokenValue::Punctuation("=".to_string()))?;
-    i += 1;
-    let (expr, j) = parse_expression(&i, toks)?;
-    if typ != expr.typ {
-        return Err(error(format!("Type mismatch: expected {:?}, but found {:?}", typ, expr.typ), toks[i].pos.clone()));
-    }
-
-    i = j;
-    expect(&i, &toks, TokenValue::Punctuation(";".to_string()))?;
-
-    global_scope.last_mut().unwrap().variables.insert(name.clone(), VariableOptions {
-        mutable: false,
-        typ: typ.clone(),
-    });
-
-    Ok((Statement {
-        kind: StatementKind::VariableDeclaration(VariableDeclaration {
-            name,
-            typ,
-            expr,
-        }),
-        pos: toks[i].pos.clone(),
-    }, i + 1, global_scope))
-}
-
-fn parse_expression_statement(i: &usize, toks: &Vec<Token>, global_scope: &mut Vec<Scope>) -> Result<(Statement, usize, Vec<Scope>), String> {
-    let mut i = *i;
-    let mut global_scope = global_scope.clone();
-    let (expr, j) = parse_expression(&i, toks)?;
-    i = j;
-    expect(&i, &toks, TokenValue::Punctuation(";".to_string()))?;
-
-    Ok((Statement {
-        kind: StatementKind::ExpressionStatement(ExpressionStatement {
-            typ: expr.clone().typ,
-            expr,
-        }),
-        pos: toks[i].pos.clone(),
-    }, j, global_scope))
-}
-
-fn parse_identifier(i: &usize, toks: &Vec<Token>, global_scope: &mut Vec<Scope>) -> Result<(Statement, usize, Vec<Scope>), String> {
-    let mut i = *i;
-    let t = toks[i].clone();
-    let val = t.value;
-
-    let stmt: Result<(Statement, usize, Vec<Scope>), String> = match val {
-        TokenValue::Identifier(ref s) => match s.as_str() {
-            "fn" => parse_function_declaration(&i, toks, global_scope),
-            "let" => parse_variable_declaration(&i, toks, global_scope),
-            _ => Err(error(format!("Unknown identifier: '{}'", s), t.pos)),
-        },
-        _ => Err(error("Expected an identifier while parsing identifier".to_string(), t.pos)),
-    };
-
-    stmt
-}
-
-fn parse_statement(i: &usize, toks: &Vec<Token>, global_scope: &mut Vec<Scope>) -> Result<(Statement, usize, Vec<Scope>), String> {
-    let mut i = *i;
-    let pos = toks[i].pos.clone();
-
-    while i < toks.len() {
-        return match &toks[i].value {
-            TokenValue::Identifier(_) => Ok(parse_identifier(&i, &toks, global_scope)?),
-            _ => Ok(parse_expression_statement(&i, &toks, global_scope)?),
-        }
-    }
-
-    Err(error("Unexpected end of file".to_string(), pos))
-}
-
-pub fn parse(toks: Vec<Token>) -> Result<Vec<Statement>, String> {
-    let mut ast: Vec<Statement> = Vec::new();
-    let mut i = 0;
-
-    let mut global_scope: Vec<Scope> = Vec::new();
-    global_scope.push(Scope { variables: HashMap::new(), functions: HashMap::new() });
-
-    while i < toks.len() {
-        let (stmt, j, scope) = parse_statement(&i, &toks, &mut global_scope)?;
-        global_scope = scope;
-        ast.push(stmt);
-        i = j;
-    }
-
-    Ok(ast)
-}
\ No newline at end of file
+use std::collections::HashMap;
+use crate::codes::{CHAINED_COMPARISON, ELSE_WITHOUT_IF, EXPECTED_EXPRESSION, INTEGER_OUT_OF_RANGE, INVALID_TYPE, NESTED_TOO_DEEPLY, NOT_AT_TOP_LEVEL, UNEXPECTED_END_OF_FILE, UNEXPECTED_TOKEN, UNSUPPORTED};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::error;
+use crate::lexer::{Keyword, StringPart, Token, TokenPos, TokenValue};
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub pos: TokenPos,
+    /// Just past the last character of the statement
+    pub end: TokenPos,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueType {
+    Integer,
+    Float,
+    String,
+    Bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum StatementKind {
+    VariableDeclaration(VariableDeclaration),
+    ConstantDeclaration(ConstantDeclaration),
+    FunctionDeclaration(FunctionDeclaration),
+    ExpressionStatement(ExpressionStatement),
+    Block(Vec<Statement>),
+    Return(ReturnStatement),
+    If(IfStatement),
+    While(WhileStatement),
+}
+
+#[derive(Debug, Clone)]
+pub struct VariableDeclaration {
+    pub name: String,
+    pub name_pos: TokenPos,
+    pub name_end: TokenPos,
+    pub annotation: Option<ValueType>,
+    pub expr: Expression,
+    /// Filled in by the resolver
+    pub symbol: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstantDeclaration {
+    pub name: String,
+    pub name_pos: TokenPos,
+    pub name_end: TokenPos,
+    /// Text of the `///` comments in front of the declaration
+    pub doc: Option<String>,
+    pub typ: ValueType,
+    pub expr: Expression,
+    /// Filled in by the resolver
+    pub symbol: Option<usize>,
+    /// Filled in by the type checker
+    pub value: Option<TokenValue>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub typ: ValueType,
+    pub pos: TokenPos,
+    pub end: TokenPos,
+    /// Filled in by the resolver
+    pub symbol: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    pub name_pos: TokenPos,
+    pub name_end: TokenPos,
+    /// Text of the `///` comments in front of the declaration
+    pub doc: Option<String>,
+    /// Declared with `pub fn`, so it is part of the module's interface
+    pub public: bool,
+    pub params: Vec<Parameter>,
+    pub typ: Option<ValueType>,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExpressionStatement {
+    pub expr: Expression,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReturnStatement {
+    pub expr: Option<Expression>,
+}
+
+/// `if condition { ... } else if condition { ... } else { ... }`, with a branch for the `if` and
+/// one for every `else if`, so a long chain stays flat instead of nesting.
+#[derive(Debug, Clone)]
+pub struct IfStatement {
+    pub branches: Vec<IfBranch>,
+    pub else_body: Option<Vec<Statement>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IfBranch {
+    pub condition: Expression,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WhileStatement {
+    pub condition: Expression,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExpressionKind {
+    Primary(PrimaryExpression),
+    Identifier(IdentifierExpression),
+    Call(CallExpression),
+    Unary(Box<UnaryExpression>),
+    Term(Box<TermExpression>),
+    Binary(Box<BinaryExpression>),
+    Comparison(Box<ComparisonExpression>),
+    Logical(Box<LogicalExpression>),
+    Bitwise(Box<BitwiseExpression>),
+    Cast(Box<CastExpression>),
+    Interpolation(InterpolationExpression),
+}
+
+#[derive(Debug, Clone)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    /// Filled in by the type checker
+    pub typ: Option<ValueType>,
+    pub pos: TokenPos,
+    /// Just past the last character of the expression
+    pub end: TokenPos,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrimaryExpression {
+    pub value: TokenValue,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdentifierExpression {
+    pub name: String,
+    /// Filled in by the resolver
+    pub symbol: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CallExpression {
+    pub name: String,
+    pub args: Vec<Expression>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UnaryExpression {
+    pub left: Expression,
+    pub op: TokenValue,
+}
+
+#[derive(Debug, Clone)]
+pub struct TermExpression {
+    pub left: Expression,
+    pub right: Expression,
+    pub op: TokenValue,
+}
+
+#[derive(Debug, Clone)]
+pub struct BinaryExpression {
+    pub left: Expression,
+    pub right: Expression,
+    pub op: TokenValue,
+}
+
+#[derive(Debug, Clone)]
+pub struct ComparisonExpression {
+    pub left: Expression,
+    pub right: Expression,
+    pub op: TokenValue,
+}
+
+/// `&&` and `||`. The right operand must only be evaluated when the left one does not already decide the result.
+#[derive(Debug, Clone)]
+pub struct LogicalExpression {
+    pub left: Expression,
+    pub right: Expression,
+    pub op: TokenValue,
+}
+
+/// `&`, `|`, `^`, `<<` and `>>`, only defined on integers.
+#[derive(Debug, Clone)]
+pub struct BitwiseExpression {
+    pub left: Expression,
+    pub right: Expression,
+    pub op: TokenValue,
+}
+
+/// Explicit `value as type` conversions, and the implicit widening of ints to floats.
+#[derive(Debug, Clone)]
+pub struct CastExpression {
+    pub left: Expression,
+    pub typ: ValueType,
+}
+
+/// An interpolated string, the concatenation of its parts converted to str.
+#[derive(Debug, Clone)]
+pub struct InterpolationExpression {
+    pub parts: Vec<Expression>,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, pos: TokenPos, end: TokenPos) -> Expression {
+        Expression { kind, typ: None, pos, end }
+    }
+
+    /// Stands in for an expression while it is moved, e.g. into a cast wrapping it.
+    pub fn placeholder(pos: TokenPos, end: TokenPos) -> Expression {
+        Expression::new(ExpressionKind::Primary(PrimaryExpression { value: TokenValue::Bool(false) }), pos, end)
+    }
+
+    /// The source text of the whole expression.
+    pub fn span(&self) -> Span {
+        Span::new(self.pos.clone(), self.end.clone())
+    }
+
+    /// The operands of a binary operator, or of a cast which has no right operand.
+    /// Chains such as `a + b + c` or `x as int as float` nest on the left without limit, so passes walk
+    /// them with [`Expression::chain`] instead of recursing into left operands.
+    pub fn operands(&self) -> Option<(&Expression, Option<&Expression>)> {
+        match &self.kind {
+            ExpressionKind::Term(term) => Some((&term.left, Some(&term.right))),
+            ExpressionKind::Binary(binary) => Some((&binary.left, Some(&binary.right))),
+            ExpressionKind::Comparison(comparison) => Some((&comparison.left, Some(&comparison.right))),
+            ExpressionKind::Logical(logical) => Some((&logical.left, Some(&logical.right))),
+            ExpressionKind::Bitwise(bitwise) => Some((&bitwise.left, Some(&bitwise.right))),
+            ExpressionKind::Cast(cast) => Some((&cast.left, None)),
+            _ => None,
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Option<(&mut Expression, Option<&mut Expression>)> {
+        match &mut self.kind {
+            ExpressionKind::Term(term) => Some((&mut term.left, Some(&mut term.right))),
+            ExpressionKind::Binary(binary) => Some((&mut binary.left, Some(&mut binary.right))),
+            ExpressionKind::Comparison(comparison) => Some((&mut comparison.left, Some(&mut comparison.right))),
+            ExpressionKind::Logical(logical) => Some((&mut logical.left, Some(&mut logical.right))),
+            ExpressionKind::Bitwise(bitwise) => Some((&mut bitwise.left, Some(&mut bitwise.right))),
+            ExpressionKind::Cast(cast) => Some((&mut cast.left, None)),
+            _ => None,
+        }
+    }
+
+    /// The chain of operators ending in `self`, innermost first: the first operand, which is not
+    /// an operator, followed by every operator applied to it in turn. Just `self` if it is no operator.
+    pub fn chain(&self) -> Vec<&Expression> {
+        let mut chain = vec![self];
+        while let Some((left, _)) = chain[chain.len() - 1].operands() {
+            chain.push(left);
+        }
+        chain.reverse();
+        chain
+    }
+
+    /// The operands of [`Expression::chain`] in source order: the first operand followed by the
+    /// right operands, for passes that modify them.
+    pub fn chain_operands_mut(&mut self) -> Vec<&mut Expression> {
+        let mut operands = Vec::new();
+        let mut node = self;
+        while node.operands().is_some() {
+            let (left, right) = node.operands_mut().expect("`node` is an operator");
+            operands.extend(right);
+            node = left;
+        }
+        operands.push(node);
+        operands.reverse();
+        operands
+    }
+
+    /// Moves out the left operand if it is an operator continuing the chain.
+    fn detach_left_operator(&mut self) -> Option<Expression> {
+        match self.operands_mut() {
+            Some((left, _)) if left.operands().is_some() => {
+                let placeholder = Expression::placeholder(left.pos.clone(), left.end.clone());
+                Some(std::mem::replace(left, placeholder))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Drop for Expression {
+    /// Unlinks the chain one operator at a time, as dropping it recursively could overflow the stack,
+    /// see [`Expression::operands`].
+    fn drop(&mut self) {
+        let mut next = self.detach_left_operator();
+        while let Some(mut expr) = next {
+            next = expr.detach_left_operator();
+        }
+    }
+}
+
+/// An error spanning the whole of `tok`.
+fn token_error(code: &'static str, message: String, tok: &Token) -> Diagnostic {
+    error(code, message, tok.pos.clone()).with_end(tok.end.clone())
+}
+
+/// An error just past the last token, for input that ends in the middle of a construct.
+fn end_of_file_error(message: String, toks: &[Token]) -> Diagnostic {
+    let end = toks.last().map_or_else(|| Token::empty().pos, |tok| tok.end.clone());
+    error(UNEXPECTED_END_OF_FILE, message, end)
+}
+
+/// Blocks nested deeper than this are rejected, so deeply nested input cannot overflow the stack.
+/// Together with [`MAX_EXPRESSION_DEPTH`] this keeps every pass within the 2 MiB stack of a spawned
+/// thread, even in a debug build.
+const MAX_BLOCK_DEPTH: usize = 64;
+
+/// Expressions nested deeper than this are rejected, see [`MAX_BLOCK_DEPTH`]. Only parentheses,
+/// prefix operators, right operands, calls and interpolations count, as each is parsed recursively.
+/// Left operands don't, so a chain like `1 + 1 + ... + 1` can be any length, see [`Expression::operands`].
+const MAX_EXPRESSION_DEPTH: usize = 32;
+
+fn expect(i: &usize, toks: &[Token], value: TokenValue) -> Result<Token, Diagnostic> {
+    let i = *i;
+    if i >= toks.len() {
+        return Err(end_of_file_error(format!("Expected {} but reached the end of file", value.describe()), toks));
+    }
+
+    // An empty value, e.g. from `TokenValue::empty("identifier")`, matches any token of that kind
+    let any_of_kind = value.as_string().is_empty() && std::mem::discriminant(&toks[i].value) == std::mem::discriminant(&value);
+    if toks[i].value == value || any_of_kind {
+        return Ok(toks[i].clone());
+    }
+    // `true` and `false` are reserved as well, they are only lexed as values
+    let reserved = matches!(toks[i].value, TokenValue::Keyword(_) | TokenValue::Bool(_));
+    if let TokenValue::Identifier(_) = &value && value.as_string().is_empty() && reserved {
+        return Err(token_error(UNEXPECTED_TOKEN, format!("'{}' is a reserved keyword and cannot be used as a name", toks[i].value.as_string()), &toks[i]));
+    }
+
+    Err(token_error(UNEXPECTED_TOKEN, format!("Expected {} but found {}", value.describe(), toks[i].value.describe()), &toks[i]))
+}
+
+pub fn parse_type(tok: &Token) -> Result<ValueType, Diagnostic> {
+    match tok.value {
+        TokenValue::Identifier(ref s) => match s.as_str() {
+            "int" => Ok(ValueType::Integer),
+            "str" => Ok(ValueType::String),
+            "float" => Ok(ValueType::Float),
+            "bool" => Ok(ValueType::Bool),
+            _ => Err(token_error(INVALID_TYPE, format!("Unknown type: '{}'", s), tok)),
+        },
+        _ => Err(token_error(INVALID_TYPE, "Expected an identifier while parsing type".to_string(), tok)),
+    }
+}
+
+fn parse_call_arguments(i: &usize, toks: &[Token], depth: usize) -> Result<(Vec<Expression>, usize), Diagnostic> {
+    let mut i = *i;
+    let mut args: Vec<Expression> = Vec::new();
+    if i < toks.len() && toks[i].value == TokenValue::Punctuation(")".to_string()) {
+        return Ok((args, i));
+    }
+    while i < toks.len() {
+        let (arg, j) = parse_expression_with_precedence(&i, toks, 0, depth)?;
+        args.push(arg);
+        i = j;
+        if i < toks.len() && toks[i].value == TokenValue::Punctuation(",".to_string()) {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    Ok((args, i))
+}
+
+fn parse_primary_expression(i: &usize, toks: &[Token], depth: usize) -> Result<(Expression, usize), Diagnostic> {
+    let mut i = *i;
+    let tok = &toks[i];
+    let expr = match &tok.value {
+        TokenValue::IntegerMagnitude(magnitude) => {
+            return Err(token_error(INTEGER_OUT_OF_RANGE, format!("Integer literal '{}' is out of range for int", magnitude), tok)
+                .with_note(format!("the largest int is {}, only the negated literal '{}' fits", i32::MAX, i32::MIN)));
+        }
+        // Input the lexer has already reported parses as a value, so it isn't reported again
+        TokenValue::Integer(_) | TokenValue::Float(_) | TokenValue::String(_) | TokenValue::Bool(_) | TokenValue::Error => {
+            Expression::new(ExpressionKind::Primary(PrimaryExpression {
+                value: tok.value.clone(),
+            }), tok.pos.clone(), tok.end.clone())
+        }
+        TokenValue::InterpolatedString(parts) => {
+            let mut exprs: Vec<Expression> = Vec::new();
+            for part in parts {
+                match part {
+                    StringPart::Literal(s) => exprs.push(Expression::new(ExpressionKind::Primary(PrimaryExpression {
+                        value: TokenValue::String(s.clone()),
+                    }), tok.pos.clone(), tok.end.clone())),
+                    // The lexer has already reported why the interpolation is empty or invalid
+                    StringPart::Expression(part_toks) if part_toks.is_empty() || part_toks.iter().any(|t| t.value == TokenValue::Error) => {}
+                    StringPart::Expression(part_toks) => {
+                        let (expr, j) = parse_expression_with_precedence(&0, part_toks, 0, depth + 1)?;
+                        if j < part_toks.len() {
+                            return Err(token_error(UNEXPECTED_TOKEN, format!("Unexpected '{}' in interpolation", part_toks[j].value.as_string()), &part_toks[j]));
+                        }
+                        exprs.push(expr);
+                    }
+                }
+            }
+            Expression::new(ExpressionKind::Interpolation(InterpolationExpression {
+                parts: exprs,
+            }), tok.pos.clone(), tok.end.clone())
+        }
+        TokenValue::Identifier(s) => {
+            if toks.get(i + 1).is_some_and(|t| t.value == TokenValue::Punctuation("(".to_string())) {
+                i += 2;
+                let (args, j) = parse_call_arguments(&i, toks, depth + 1)?;
+                i = j;
+                let close = expect(&i, toks, TokenValue::Punctuation(")".to_string()))?;
+                Expression::new(ExpressionKind::Call(CallExpression {
+                    name: s.clone(),
+                    args,
+                }), tok.pos.clone(), close.end)
+            } else {
+                Expression::new(ExpressionKind::Identifier(IdentifierExpression {
+                    name: s.clone(),
+                    symbol: None,
+                }), tok.pos.clone(), tok.end.clone())
+            }
+        },
+        TokenValue::Punctuation(p) if p == "(" => {
+            i += 1;
+            let (expr, j) = parse_expression_with_precedence(&i, toks, 0, depth + 1)?;
+            i = j;
+            expect(&i, toks, TokenValue::Punctuation(")".to_string()))?;
+            expr
+        }
+        _ => return Err(token_error(EXPECTED_EXPRESSION, "Expected a primary expression".to_string(), tok)),
+    };
+    i += 1;
+    Ok((expr, i))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Associativity {
+    Left,
+    /// Chaining is an error, e.g. `a < b < c`
+    None,
+}
+
+/// Binding power of the prefix operators `-`, `!` and `~`, tighter than every binary operator.
+const PREFIX_PRECEDENCE: u8 = 11;
+
+/// Binding power of the postfix `as` cast, so `-x as float * 2` is `((-x) as float) * 2`.
+const CAST_PRECEDENCE: u8 = 10;
+
+/// Precedence and associativity of every binary operator. Higher binds tighter, see
+/// [`CAST_PRECEDENCE`] and [`PREFIX_PRECEDENCE`] for the remaining operators:
+///
+/// | Precedence | Operators                   | Associativity |
+/// |------------|-----------------------------|---------------|
+/// | 1          | `\|\|`                      | left          |
+/// | 2          | `&&`                        | left          |
+/// | 3          | `==` `!=` `<` `>` `<=` `>=` | none          |
+/// | 4          | `\|`                        | left          |
+/// | 5          | `^`                         | left          |
+/// | 6          | `&`                         | left          |
+/// | 7          | `<<` `>>`                   | left          |
+/// | 8          | `+` `-`                     | left          |
+/// | 9          | `*` `/` `%`                 | left          |
+fn binary_operator(value: &TokenValue) -> Option<(u8, Associativity)> {
+    let precedence = match value {
+        TokenValue::Logical(op) => match op.as_str() {
+            "||" => 1,
+            "&&" => 2,
+            _ => return None,
+        },
+        TokenValue::Arithmetic(op) => match op.as_str() {
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => return Some((3, Associativity::None)),
+            "+" | "-" => 8,
+            "*" | "/" | "%" => 9,
+            _ => return None,
+        },
+        TokenValue::Bitwise(op) => match op.as_str() {
+            "|" => 4,
+            "^" => 5,
+            "&" => 6,
+            "<<" | ">>" => 7,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some((precedence, Associativity::Left))
+}
+
+fn is_prefix_operator(value: &TokenValue) -> bool {
+    matches!(value, TokenValue::Arithmetic(op) | TokenValue::Logical(op) | TokenValue::Bitwise(op) if op == "-" || op == "!" || op == "~")
+}
+
+fn parse_prefix_expression(i: &usize, toks: &[Token], depth: usize) -> Result<(Expression, usize), Diagnostic> {
+    let mut i = *i;
+    let Some(tok) = toks.get(i) else {
+        return Err(end_of_file_error("Expected an expression but reached the end of file".to_string(), toks));
+    };
+    if depth > MAX_EXPRESSION_DEPTH {
+        return Err(token_error(NESTED_TOO_DEEPLY, format!("Expression is nested too deeply, the limit is {} levels", MAX_EXPRESSION_DEPTH), tok));
+    }
+    if !is_prefix_operator(&tok.value) {
+        if binary_operator(&tok.value).is_some() {
+            return Err(token_error(EXPECTED_EXPRESSION, format!("Expected an expression but found operator '{}', which is not a prefix operator", tok.value.as_string()), tok));
+        }
+        return parse_primary_expression(&i, toks, depth);
+    }
+
+    i += 1;
+    // The smallest int can only be written as a negated literal, see `TokenValue::IntegerMagnitude`
+    if tok.value == TokenValue::Arithmetic("-".to_string()) && let Some(literal) = toks.get(i)
+        && let TokenValue::IntegerMagnitude(magnitude) = literal.value && let Ok(value) = i32::try_from(-i64::from(magnitude)) {
+        return Ok((Expression::new(ExpressionKind::Primary(PrimaryExpression {
+            value: TokenValue::Integer(value),
+        }), tok.pos.clone(), literal.end.clone()), i + 1));
+    }
+    let (expr, j) = parse_expression_with_precedence(&i, toks, PREFIX_PRECEDENCE, depth + 1)?;
+    let end = expr.end.clone();
+    Ok((Expression::new(ExpressionKind::Unary(Box::from(UnaryExpression {
+        left: expr,
+        op: tok.clone().value,
+    })), tok.pos.clone(), end), j))
+}
+
+fn make_binary_expression(tok: &Token, left: Expression, right: Expression) -> Expression {
+    let (pos, end) = (left.pos.clone(), right.end.clone());
+    let op = tok.value.clone();
+    let kind = match op.as_string().as_str() {
+        "||" | "&&" => ExpressionKind::Logical(Box::from(LogicalExpression { left, right, op })),
+        "==" | "!=" | "<" | ">" | "<=" | ">=" => ExpressionKind::Comparison(Box::from(ComparisonExpression { left, right, op })),
+        "|" | "^" | "&" | "<<" | ">>" => ExpressionKind::Bitwise(Box::from(BitwiseExpression { left, right, op })),
+        "+" | "-" => ExpressionKind::Binary(Box::from(BinaryExpression { left, right, op })),
+        _ => ExpressionKind::Term(Box::from(TermExpression { left, right, op })),
+    };
+    Expression::new(kind, pos, end)
+}
+
+/// Pratt parser: parses operands and every binary operator binding at least as tight as `min_precedence`.
+/// `depth` is how deeply the expression is nested, see [`MAX_EXPRESSION_DEPTH`]. Operators are folded
+/// into the left operand in a loop, so only the right operands are parsed one level deeper.
+fn parse_expression_with_precedence(i: &usize, toks: &[Token], min_precedence: u8, depth: usize) -> Result<(Expression, usize), Diagnostic> {
+    let (mut expr, mut i) = parse_prefix_expression(i, toks, depth)?;
+    let mut previous: Option<(u8, Associativity)> = None;
+    while i < toks.len() {
+        let tok = &toks[i];
+        if tok.value == TokenValue::Keyword(Keyword::As) && CAST_PRECEDENCE >= min_precedence {
+            i += 1;
+            let Some(type_tok) = toks.get(i) else {
+                return Err(token_error(INVALID_TYPE, "Expected a type after 'as'".to_string(), tok));
+            };
+            let typ = parse_type(type_tok)?;
+            i += 1;
+            let pos = expr.pos.clone();
+            expr = Expression::new(ExpressionKind::Cast(Box::from(CastExpression {
+                left: expr,
+                typ,
+            })), pos, type_tok.end.clone());
+            continue;
+        }
+        let Some((precedence, associativity)) = binary_operator(&tok.value) else {
+            break;
+        };
+        if precedence < min_precedence {
+            break;
+        }
+        if associativity == Associativity::None && previous == Some((precedence, associativity)) {
+            return Err(token_error(CHAINED_COMPARISON, format!("Comparison operator '{}' cannot be chained, use parentheses to group comparisons", tok.value.as_string()), tok));
+        }
+
+        i += 1;
+        if i >= toks.len() {
+            return Err(token_error(EXPECTED_EXPRESSION, format!("Expected an expression after operator '{}'", tok.value.as_string()), tok));
+        }
+        let (right, j) = parse_expression_with_precedence(&i, toks, precedence + 1, depth + 1)?;
+        i = j;
+        expr = make_binary_expression(tok, expr, right);
+        previous = Some((precedence, associativity));
+    }
+    Ok((expr, i))
+}
+
+fn parse_expression(i: &usize, toks: &[Token]) -> Result<(Expression, usize), Diagnostic> {
+    parse_expression_with_precedence(i, toks, 0, 0)
+}
+
+/// Skips the rest of a statement that failed to parse: up to and including the next `;`,
+/// or up to the next `}` or statement keyword, whichever comes first. Blocks opened by the
+/// statement itself are skipped as a whole, along with the `else` and `else if` branches following them.
+fn synchronize(i: &usize, toks: &[Token]) -> usize {
+    // The name of a declaration is skipped even if it is a keyword, e.g. the `if` in `let if = 1;`
+    let declaration = matches!(toks[*i].value, TokenValue::Keyword(Keyword::Let | Keyword::Const));
+    let keyword_name = toks.get(*i + 1).is_some_and(|tok| matches!(tok.value, TokenValue::Keyword(_) | TokenValue::Bool(_)));
+    let mut i = *i + if declaration && keyword_name { 2 } else { 1 };
+    let mut depth = 0;
+    while i < toks.len() {
+        match &toks[i].value {
+            TokenValue::Punctuation(p) if p == "{" => depth += 1,
+            TokenValue::Punctuation(p) if p == "}" && depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    if toks.get(i + 1).is_none_or(|tok| tok.value != TokenValue::Keyword(Keyword::Else)) {
+                        return i + 1;
+                    }
+                    // The `if` of an `else if` continues the statement rather than starting one
+                    i += 1;
+                    if toks.get(i + 1).is_some_and(|tok| tok.value == TokenValue::Keyword(Keyword::If)) {
+                        i += 1;
+                    }
+                }
+            }
+            _ if depth > 0 => {}
+            TokenValue::Punctuation(p) if p == ";" => return i + 1,
+            TokenValue::Punctuation(p) if p == "}" => return i,
+            TokenValue::Keyword(keyword) if !matches!(keyword, Keyword::As | Keyword::Else) => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    i
+}
+
+/// Whether `err` is likely a consequence of input the lexer has already reported, i.e. an
+/// error token comes before it in `toks`, the tokens of the statement that failed to parse.
+fn follows_lexer_error(err: &Diagnostic, toks: &[Token]) -> bool {
+    let at = (err.span.start.line, err.span.start.col);
+    toks.iter().any(|tok| tok.value == TokenValue::Error && (tok.pos.line, tok.pos.col) <= at)
+}
+
+/// Parses statements up to the closing `}` of a body. A statement that fails to parse is
+/// recorded in `errors` and skipped, so the rest of the body is still checked.
+fn parse_body(i: &usize, toks: &[Token], errors: &mut Vec<Diagnostic>) -> Result<(Vec<Statement>, usize), Diagnostic> {
+    let mut i = *i;
+    let mut body: Vec<Statement> = Vec::new();
+    while i < toks.len() {
+        let tok = &toks[i];
+        if let TokenValue::Punctuation(p) = &tok.value {
+            if p == "}" {
+                break;
+            } else if p == "{" {
+                i += 1;
+                let (nested_body, j) = parse_body(&i, toks, errors)?;
+                i = j;
+                let close = expect(&i, toks, TokenValue::Punctuation("}".to_string()))?;
+                i += 1;
+                body.push(Statement {
+                    kind: StatementKind::Block(nested_body),
+                    pos: tok.pos.clone(),
+                    end: close.end,
+                });
+                continue;
+            }
+        }
+        match parse_statement(&i, toks, errors) {
+            Ok((stmt, j)) => {
+                i = j;
+                body.push(stmt);
+            }
+            Err(err) => {
+                let j = synchronize(&i, toks);
+                if !follows_lexer_error(&err, &toks[i..j]) && !errors.contains(&err) {
+                    errors.push(err);
+                }
+                i = j;
+            }
+        }
+    }
+    Ok((body, i))
+}
+
+/// Classes are reserved syntax, so their header is checked but the declaration is always rejected.
+fn parse_class_declaration(i: &usize, toks: &[Token]) -> Result<(Statement, usize), Diagnostic> {
+    let class = &toks[*i];
+    let name = expect(&(*i + 1), toks, TokenValue::empty("identifier")?)?.value.as_string();
+    expect(&(*i + 2), toks, TokenValue::Punctuation("{".to_string()))?;
+    Err(token_error(UNSUPPORTED, format!("Class '{}' cannot be declared, classes are not supported yet", name), class))
+}
+
+fn parse_declaration_arguments(i: &usize, toks: &[Token]) -> Result<(Vec<Parameter>, usize), Diagnostic> {
+    let mut i = *i;
+    let mut args: Vec<Parameter> = Vec::new();
+    while i < toks.len() {
+        let tok = &toks[i];
+        if let TokenValue::Identifier(name) = &tok.value {
+            i += 1;
+            expect(&i, toks, TokenValue::Punctuation(":".to_string()))?;
+            i += 1;
+            let type_ident = expect(&i, toks, TokenValue::empty("identifier")?)?;
+            let typ = parse_type(&type_ident)?;
+            i += 1;
+            args.push(Parameter {
+                name: name.clone(),
+                typ,
+                pos: tok.pos.clone(),
+                end: tok.end.clone(),
+                symbol: None,
+            });
+            if let Some(TokenValue::Punctuation(p)) = toks.get(i).map(|t| &t.value) {
+                if p == "," {
+                    i += 1;
+                } else {
+                    break;
+                }
+            } else {
+                break;
+            }
+        } else {
+            break;
+        }
+    }
+    Ok((args, i))
+}
+
+fn parse_function_declaration(i: &usize, toks: &[Token], errors: &mut Vec<Diagnostic>) -> Result<(Statement, usize), Diagnostic> {
+    let mut i = *i;
+    let pos = toks[i].pos.clone();
+    let public = toks[i].value == TokenValue::Keyword(Keyword::Pub);
+    if public {
+        i += 1;
+        expect(&i, toks, TokenValue::Keyword(Keyword::Fn))?;
+    }
+    i += 1;
+    let name_tok = expect(&i, toks, TokenValue::empty("identifier")?)?;
+    i += 1;
+    expect(&i, toks, TokenValue::Punctuation("(".to_string()))?;
+    i += 1;
+    let (params, j) = parse_declaration_arguments(&i, toks)?;
+    i = j;
+    expect(&i, toks, TokenValue::Punctuation(")".to_string()))?;
+    i += 1;
+    let typ: Option<ValueType>;
+    if let Ok(a) = expect(&i, toks, TokenValue::Punctuation("->".to_string())) {
+        if a.value != TokenValue::Punctuation("->".to_string()) {
+            typ = None
+        } else {
+            i += 1;
+            typ = Some(parse_type(&expect(&i, toks, TokenValue::empty("identifier")?)?)?);
+            i += 1;
+        }
+    } else {
+        typ = None;
+    }
+    expect(&i, toks, TokenValue::Punctuation("{".to_string()))?;
+    i += 1;
+    let (body, j) = parse_body(&i, toks, errors)?;
+    i = j;
+    let close = expect(&i, toks, TokenValue::Punctuation("}".to_string()))?;
+    i += 1;
+
+    Ok((Statement {
+        kind: StatementKind::FunctionDeclaration(FunctionDeclaration {
+            name: name_tok.value.as_string(),
+            name_pos: name_tok.pos,
+            name_end: name_tok.end,
+            doc: None,
+            public,
+            params,
+            typ,
+            body,
+        }),
+        pos,
+        end: close.end,
+    }, i))
+}
+
+fn parse_constant_declaration(i: &usize, toks: &[Token]) -> Result<(Statement, usize), Diagnostic> {
+    let mut i = *i;
+    let pos = toks[i].pos.clone();
+    i += 1;
+    let name_tok = expect(&i, toks, TokenValue::empty("identifier")?)?;
+    i += 1;
+    expect(&i, toks, TokenValue::Punctuation(":".to_string()))?;
+    i += 1;
+    let type_ident = expect(&i, toks, TokenValue::empty("identifier")?)?;
+    let typ = parse_type(&type_ident)?;
+    i += 1;
+    expect(&i, toks, TokenValue::Punctuation("=".to_string()))?;
+    i += 1;
+    let (expr, j) = parse_expression(&i, toks)?;
+    i = j;
+    let semicolon = expect(&i, toks, TokenValue::Punctuation(";".to_string()))?;
+
+    Ok((Statement {
+        kind: StatementKind::ConstantDeclaration(ConstantDeclaration {
+            name: name_tok.value.as_string(),
+            name_pos: name_tok.pos,
+            name_end: name_tok.end,
+            doc: None,
+            typ,
+            expr,
+            symbol: None,
+            value: None,
+        }),
+        pos,
+        end: semicolon.end,
+    }, i + 1))
+}
+
+fn parse_variable_declaration(i: &usize, toks: &[Token]) -> Result<(Statement, usize), Diagnostic> {
+    let mut i = *i;
+    let pos = toks[i].pos.clone();
+    i += 1;
+    let name_tok = expect(&i, toks, TokenValue::empty("identifier")?)?;
+    i += 1;
+    let mut annotation: Option<ValueType> = None;
+    if i < toks.len() && toks[i].value == TokenValue::Punctuation(":".to_string()) {
+        i += 1;
+        let type_ident = expect(&i, toks, TokenValue::empty("identifier")?)?;
+        annotation = Some(parse_type(&type_ident)?);
+        i += 1;
+    }
+    expect(&i, toks, TokenValue::Punctuation("=".to_string()))?;
+    i += 1;
+    let (expr, j) = parse_expression(&i, toks)?;
+    i = j;
+    let semicolon = expect(&i, toks, TokenValue::Punctuation(";".to_string()))?;
+
+    Ok((Statement {
+        kind: StatementKind::VariableDeclaration(VariableDeclaration {
+            name: name_tok.value.as_string(),
+            name_pos: name_tok.pos,
+            name_end: name_tok.end,
+            annotation,
+            expr,
+            symbol: None,
+        }),
+        pos,
+        end: semicolon.end,
+    }, i + 1))
+}
+
+fn parse_expression_statement(i: &usize, toks: &[Token]) -> Result<(Statement, usize), Diagnostic> {
+    let mut i = *i;
+    let pos = toks[i].pos.clone();
+    let (expr, j) = parse_expression(&i, toks)?;
+    i = j;
+    let semicolon = expect(&i, toks, TokenValue::Punctuation(";".to_string()))?;
+
+    Ok((Statement {
+        kind: StatementKind::ExpressionStatement(ExpressionStatement {
+            expr,
+        }),
+        pos,
+        end: semicolon.end,
+    }, i + 1))
+}
+
+/// Parses a body enclosed in braces.
+fn parse_block(i: &usize, toks: &[Token], errors: &mut Vec<Diagnostic>) -> Result<(Vec<Statement>, usize), Diagnostic> {
+    let mut i = *i;
+    expect(&i, toks, TokenValue::Punctuation("{".to_string()))?;
+    i += 1;
+    let (body, j) = parse_body(&i, toks, errors)?;
+    i = j;
+    expect(&i, toks, TokenValue::Punctuation("}".to_string()))?;
+    Ok((body, i + 1))
+}
+
+fn parse_return_statement(i: &usize, toks: &[Token]) -> Result<(Statement, usize), Diagnostic> {
+    let mut i = *i;
+    let pos = toks[i].pos.clone();
+    i += 1;
+    let mut expr = None;
+    if i < toks.len() && toks[i].value != TokenValue::Punctuation(";".to_string()) {
+        let (value, j) = parse_expression(&i, toks)?;
+        expr = Some(value);
+        i = j;
+    }
+    let semicolon = expect(&i, toks, TokenValue::Punctuation(";".to_string()))?;
+
+    Ok((Statement {
+        kind: StatementKind::Return(ReturnStatement { expr }),
+        pos,
+        end: semicolon.end,
+    }, i + 1))
+}
+
+fn parse_if_statement(i: &usize, toks: &[Token], errors: &mut Vec<Diagnostic>) -> Result<(Statement, usize), Diagnostic> {
+    let mut i = *i;
+    let pos = toks[i].pos.clone();
+    let mut branches = Vec::new();
+    let mut else_body = None;
+    // Each pass parses the `if` and its block, then continues while an `else if` follows
+    loop {
+        i += 1;
+        let (condition, j) = parse_expression(&i, toks)?;
+        let (body, j) = parse_block(&j, toks, errors)?;
+        i = j;
+        branches.push(IfBranch { condition, body });
+        if i < toks.len() && toks[i].value == TokenValue::Keyword(Keyword::Else) {
+            i += 1;
+            if i < toks.len() && toks[i].value == TokenValue::Keyword(Keyword::If) {
+                continue;
+            }
+            let (body, j) = parse_block(&i, toks, errors)?;
+            else_body = Some(body);
+            i = j;
+        }
+        break;
+    }
+
+    Ok((Statement {
+        kind: StatementKind::If(IfStatement { branches, else_body }),
+        pos,
+        end: toks[i - 1].end.clone(),
+    }, i))
+}
+
+fn parse_while_statement(i: &usize, toks: &[Token], errors: &mut Vec<Diagnostic>) -> Result<(Statement, usize), Diagnostic> {
+    let mut i = *i;
+    let pos = toks[i].pos.clone();
+    i += 1;
+    let (condition, j) = parse_expression(&i, toks)?;
+    let (body, j) = parse_block(&j, toks, errors)?;
+
+    Ok((Statement {
+        kind: StatementKind::While(WhileStatement { condition, body }),
+        pos,
+        end: toks[j - 1].end.clone(),
+    }, j))
+}
+
+/// Parses a statement starting with a keyword, e.g. `let` or `while`.
+fn parse_keyword_statement(i: &usize, toks: &[Token], keyword: Keyword, errors: &mut Vec<Diagnostic>) -> Result<(Statement, usize), Diagnostic> {
+    let tok = &toks[*i];
+    match keyword {
+        Keyword::Fn | Keyword::Pub => Err(token_error(NOT_AT_TOP_LEVEL, "Functions can only be declared at the top level".to_string(), tok)),
+        Keyword::Const => Err(token_error(NOT_AT_TOP_LEVEL, "Constants can only be declared at the top level".to_string(), tok)),
+        Keyword::Class => Err(token_error(NOT_AT_TOP_LEVEL, "Classes can only be declared at the top level".to_string(), tok)),
+        Keyword::Let => parse_variable_declaration(i, toks),
+        Keyword::Return => parse_return_statement(i, toks),
+        Keyword::If => parse_if_statement(i, toks, errors),
+        Keyword::While => parse_while_statement(i, toks, errors),
+        Keyword::Else => Err(token_error(ELSE_WITHOUT_IF, "'else' without a matching 'if'".to_string(), tok)),
+        Keyword::As => parse_expression_statement(i, toks),
+    }
+}
+
+fn parse_statement(i: &usize, toks: &[Token], errors: &mut Vec<Diagnostic>) -> Result<(Statement, usize), Diagnostic> {
+    let i = *i;
+    if i < toks.len() {
+        return match &toks[i].value {
+            TokenValue::Keyword(keyword) => parse_keyword_statement(&i, toks, *keyword, errors),
+            _ => parse_expression_statement(&i, toks),
+        }
+    }
+
+    Err(end_of_file_error("Unexpected end of file".to_string(), toks))
+}
+
+/// Removes the doc comments from the tokens, returning the text of each run of `///` lines
+/// keyed by the index of the token that follows it.
+fn take_doc_comments(toks: Vec<Token>) -> (Vec<Token>, HashMap<usize, String>) {
+    let mut code: Vec<Token> = Vec::with_capacity(toks.len());
+    let mut docs: HashMap<usize, String> = HashMap::new();
+    for tok in toks {
+        if let TokenValue::DocComment(text) = tok.value {
+            let doc = docs.entry(code.len()).or_default();
+            if !doc.is_empty() {
+                doc.push('\n');
+            }
+            doc.push_str(&text);
+        } else {
+            code.push(tok);
+        }
+    }
+    (code, docs)
+}
+
+/// Attaches a doc comment to the declaration it precedes. Doc comments anywhere else are
+/// treated as plain comments.
+fn attach_doc(stmt: &mut Statement, doc: String) {
+    match &mut stmt.kind {
+        StatementKind::FunctionDeclaration(function) => function.doc = Some(doc),
+        StatementKind::ConstantDeclaration(constant) => constant.doc = Some(doc),
+        _ => {}
+    }
+}
+
+/// Parses the tokens into a syntax tree. Names and types are left unresolved,
+/// see [`crate::resolver::resolve`] and [`crate::typechecker::check`].
+///
+/// Parsing recovers from errors by skipping to the next statement, so all of them are
+/// returned together. The syntax tree is only usable if there were none, and the lexer
+/// reported none either.
+pub fn parse(toks: Vec<Token>) -> (Vec<Statement>, Vec<Diagnostic>) {
+    let (toks, mut docs) = take_doc_comments(toks);
+    let mut ast: Vec<Statement> = Vec::new();
+    let mut errors: Vec<Diagnostic> = Vec::new();
+    let mut i = 0;
+
+    // Bodies are parsed recursively, so nesting is checked up front
+    let mut depth = 0;
+    for tok in &toks {
+        if tok.value == TokenValue::Punctuation("{".to_string()) {
+            depth += 1;
+            if depth > MAX_BLOCK_DEPTH {
+                errors.push(token_error(NESTED_TOO_DEEPLY, format!("Block is nested too deeply, the limit is {} levels", MAX_BLOCK_DEPTH), tok));
+                return (ast, errors);
+            }
+        } else if tok.value == TokenValue::Punctuation("}".to_string()) {
+            depth = depth.saturating_sub(1);
+        }
+    }
+
+    while i < toks.len() {
+        let result = match &toks[i].value {
+            TokenValue::Keyword(Keyword::Const) => parse_constant_declaration(&i, &toks),
+            TokenValue::Keyword(Keyword::Fn | Keyword::Pub) => parse_function_declaration(&i, &toks, &mut errors),
+            TokenValue::Keyword(Keyword::Class) => parse_class_declaration(&i, &toks),
+            _ => parse_statement(&i, &toks, &mut errors),
+        };
+        match result {
+            Ok((mut stmt, j)) => {
+                if let Some(doc) = docs.remove(&i) {
+                    attach_doc(&mut stmt, doc);
+                }
+                ast.push(stmt);
+                i = j;
+            }
+            Err(err) => {
+                let j = if let TokenValue::Keyword(Keyword::Fn | Keyword::Pub) = &toks[i].value {
+                    // The body of a function that failed to parse is skipped entirely,
+                    // as functions are only declared at the top level. The search starts after the
+                    // `fn` of a `pub fn`, which would otherwise stop it right away.
+                    let mut j = i + 1;
+                    if toks[i].value == TokenValue::Keyword(Keyword::Pub) && toks.get(j).is_some_and(|tok| tok.value == TokenValue::Keyword(Keyword::Fn)) {
+                        j += 1;
+                    }
+                    while j < toks.len() && !matches!(&toks[j].value, TokenValue::Keyword(Keyword::Fn | Keyword::Pub | Keyword::Const | Keyword::Class)) {
+                        j += 1;
+                    }
+                    j
+                } else {
+                    synchronize(&i, &toks)
+                };
+                if !follows_lexer_error(&err, &toks[i..j]) && !errors.contains(&err) {
+                    errors.push(err);
+                }
+                i = j;
+            }
+        }
+    }
+
+    (ast, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+
+    /// The expression with every operator in parentheses.
+    fn shape(expr: &Expression) -> String {
+        match &expr.kind {
+            ExpressionKind::Primary(primary) => primary.value.as_string(),
+            ExpressionKind::Identifier(identifier) => identifier.name.clone(),
+            ExpressionKind::Unary(unary) => format!("({}{})", unary.op.as_string(), shape(&unary.left)),
+            ExpressionKind::Cast(cast) => format!("({} as {:?})", shape(&cast.left), cast.typ),
+            ExpressionKind::Term(term) => format!("({} {} {})", shape(&term.left), term.op.as_string(), shape(&term.right)),
+            ExpressionKind::Binary(binary) => format!("({} {} {})", shape(&binary.left), binary.op.as_string(), shape(&binary.right)),
+            ExpressionKind::Comparison(comparison) => format!("({} {} {})", shape(&comparison.left), comparison.op.as_string(), shape(&comparison.right)),
+            ExpressionKind::Logical(logical) => format!("({} {} {})", shape(&logical.left), logical.op.as_string(), shape(&logical.right)),
+            ExpressionKind::Bitwise(bitwise) => format!("({} {} {})", shape(&bitwise.left), bitwise.op.as_string(), shape(&bitwise.right)),
+            ExpressionKind::Call(_) | ExpressionKind::Interpolation(_) => panic!("not used in these tests"),
+        }
+    }
+
+    fn parse_shape(source: &str) -> String {
+        let (toks, errors) = lex(format!("let m = {};", source), "test.zk".to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (ast, errors) = parse(toks);
+        assert!(errors.is_empty(), "{:?}", errors);
+        match &ast[0].kind {
+            StatementKind::VariableDeclaration(decl) => shape(&decl.expr),
+            kind => panic!("expected a declaration, got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn left_associative() {
+        assert_eq!(parse_shape("1 - 2 - 3"), "((1 - 2) - 3)");
+        assert_eq!(parse_shape("7 % 3 * 2"), "((7 % 3) * 2)");
+        assert_eq!(parse_shape("8 / 4 % 3"), "((8 / 4) % 3)");
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parse_shape("1 + 2 * 3"), "(1 + (2 * 3))");
+        assert_eq!(parse_shape("-x as float * 2"), "(((-x) as Float) * 2)");
+        assert_eq!(parse_shape("a & b == c"), "((a & b) == c)");
+        assert_eq!(parse_shape("a | b ^ c & d"), "(a | (b ^ (c & d)))");
+        assert_eq!(parse_shape("a || b && c == d"), "(a || (b && (c == d)))");
+        assert_eq!(parse_shape("1 << 2 + 3"), "(1 << (2 + 3))");
+    }
+
+    #[test]
+    fn smallest_int() {
+        assert_eq!(parse_shape("-2147483648"), "-2147483648");
+        assert_eq!(parse_shape("-0x80000000 - 1"), "(-2147483648 - 1)");
+        for source in ["let m = 2147483648;", "let m = -(2147483648);", "let m = 1 - 2147483648;"] {
+            let (toks, _) = lex(source.to_string(), "test.zk".to_string());
+            let (_, errors) = parse(toks);
+            assert_eq!(errors.iter().map(|err| err.code).collect::<Vec<_>>(), [Some(INTEGER_OUT_OF_RANGE)], "{}", source);
+        }
+    }
+
+    #[test]
+    fn comparisons_do_not_chain() {
+        let (toks, _) = lex("let m = a < b < c;".to_string(), "test.zk".to_string());
+        let (_, errors) = parse(toks);
+        assert_eq!(errors.iter().map(|err| err.code).collect::<Vec<_>>(), [Some(CHAINED_COMPARISON)]);
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use crate::codes::{ALREADY_DECLARED, SHADOWED_NAME, UNKNOWN_FUNCTION, UNKNOWN_IDENTIFIER};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::span_error;
+use crate::lexer::TokenValue;
+use crate::parser::{Expression, ExpressionKind, Statement, StatementKind, ValueType};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Constant,
+}
+
+/// A declared variable, parameter or constant. Declarations and identifiers refer to it by its index in [`Symbols::symbols`].
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub mutable: bool,
+    /// The name in the declaration
+    pub span: Span,
+    /// Filled in by the type checker
+    pub typ: Option<ValueType>,
+    /// Where the type was inferred from, `None` if it was written out
+    pub inferred: Option<Span>,
+    /// The value of a constant, filled in by the type checker
+    pub value: Option<TokenValue>,
+    /// Whether any identifier refers to it
+    pub used: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionOptions {
+    pub args: Vec<ValueType>,
+    pub typ: Option<ValueType>,
+    /// The name in the declaration
+    pub span: Span,
+    pub public: bool,
+    /// Whether the function is called anywhere
+    pub used: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    pub symbols: Vec<Symbol>,
+    pub functions: HashMap<String, FunctionOptions>,
+    /// Report declarations that shadow a name from an enclosing scope
+    pub warn_shadowing: bool,
+    pub warnings: Vec<Diagnostic>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Scope {
+    names: HashMap<String, usize>,
+}
+
+fn declare(name: &str, kind: SymbolKind, span: Span, symbols: &mut Symbols, scopes: &mut [Scope]) -> Result<usize, Diagnostic> {
+    let (scope, outer) = scopes.split_last_mut().unwrap();
+    if let Some(&existing) = scope.names.get(name) {
+        let what = match symbols.symbols[existing].kind {
+            SymbolKind::Variable => "Variable",
+            SymbolKind::Parameter => "Parameter",
+            SymbolKind::Constant => "Constant",
+        };
+        let first = symbols.symbols[existing].span.clone();
+        return Err(span_error(ALREADY_DECLARED, format!("{} '{}' already declared", what, name), span)
+            .with_label(first, format!("'{}' first declared here", name)));
+    }
+
+    // Shadowing a name from an enclosing scope is allowed, but can be reported
+    if symbols.warn_shadowing && let Some(shadowed) = lookup(name, outer) {
+        let shadowed = symbols.symbols[shadowed].span.clone();
+        symbols.warnings.push(Diagnostic::warning(format!("'{}' shadows a declaration from an enclosing scope", name), span.start.clone())
+            .with_end(span.end.clone())
+            .with_code(SHADOWED_NAME)
+            .with_label(shadowed, format!("'{}' previously declared here", name)));
+    }
+
+    symbols.symbols.push(Symbol {
+        name: name.to_string(),
+        kind,
+        mutable: false,
+        span,
+        typ: None,
+        inferred: None,
+        value: None,
+        used: false,
+    });
+    let id = symbols.symbols.len() - 1;
+    scope.names.insert(name.to_string(), id);
+    Ok(id)
+}
+
+fn lookup(name: &str, scopes: &[Scope]) -> Option<usize> {
+    scopes.iter().rev().find_map(|s| s.names.get(name).copied())
+}
+
+fn resolve_expression(expr: &mut Expression, symbols: &mut Symbols, scopes: &[Scope]) -> Result<(), Diagnostic> {
+    if expr.operands().is_some() {
+        for operand in expr.chain_operands_mut() {
+            resolve_expression(operand, symbols, scopes)?;
+        }
+        return Ok(());
+    }
+    let span = expr.span();
+    match &mut expr.kind {
+        ExpressionKind::Primary(_) => {}
+        ExpressionKind::Identifier(identifier) => {
+            let id = lookup(&identifier.name, scopes)
+                .ok_or_else(|| span_error(UNKNOWN_IDENTIFIER, format!("Unknown identifier: '{}'", identifier.name), span))?;
+            symbols.symbols[id].used = true;
+            identifier.symbol = Some(id);
+        }
+        ExpressionKind::Call(call) => {
+            let Some(function) = symbols.functions.get_mut(&call.name) else {
+                return Err(span_error(UNKNOWN_FUNCTION, format!("Unknown function: '{}'", call.name), span));
+            };
+            function.used = true;
+            for arg in &mut call.args {
+                resolve_expression(arg, symbols, scopes)?;
+            }
+        }
+        ExpressionKind::Unary(unary) => resolve_expression(&mut unary.left, symbols, scopes)?,
+        ExpressionKind::Interpolation(interpolation) => {
+            for part in &mut interpolation.parts {
+                resolve_expression(part, symbols, scopes)?;
+            }
+        }
+        ExpressionKind::Term(_) | ExpressionKind::Binary(_) | ExpressionKind::Comparison(_) | ExpressionKind::Logical(_)
+        | ExpressionKind::Bitwise(_) | ExpressionKind::Cast(_) => unreachable!("operators are resolved through `chain_operands_mut`"),
+    }
+    Ok(())
+}
+
+/// Resolves the statements of a block in a new scope.
+fn resolve_body(body: &mut [Statement], symbols: &mut Symbols, scopes: &mut Vec<Scope>) -> Result<(), Diagnostic> {
+    scopes.push(Scope::default());
+    for stmt in body {
+        resolve_statement(stmt, symbols, scopes)?;
+    }
+    scopes.pop();
+    Ok(())
+}
+
+fn resolve_statement(stmt: &mut Statement, symbols: &mut Symbols, scopes: &mut Vec<Scope>) -> Result<(), Diagnostic> {
+    match &mut stmt.kind {
+        StatementKind::VariableDeclaration(decl) => {
+            // Resolved before declaring, so the initialiser cannot refer to the variable itself
+            resolve_expression(&mut decl.expr, symbols, scopes)?;
+            decl.symbol = Some(declare(&decl.name, SymbolKind::Variable, Span::new(decl.name_pos.clone(), decl.name_end.clone()), symbols, scopes)?);
+        }
+        StatementKind::ConstantDeclaration(decl) => {
+            resolve_expression(&mut decl.expr, symbols, scopes)?;
+            decl.symbol = Some(declare(&decl.name, SymbolKind::Constant, Span::new(decl.name_pos.clone(), decl.name_end.clone()), symbols, scopes)?);
+        }
+        StatementKind::FunctionDeclaration(function) => {
+            scopes.push(Scope::default());
+            for param in &mut function.params {
+                param.symbol = Some(declare(&param.name, SymbolKind::Parameter, Span::new(param.pos.clone(), param.end.clone()), symbols, scopes)?);
+            }
+            for stmt in &mut function.body {
+                resolve_statement(stmt, symbols, scopes)?;
+            }
+            scopes.pop();
+        }
+        StatementKind::ExpressionStatement(expr_stmt) => resolve_expression(&mut expr_stmt.expr, symbols, scopes)?,
+        StatementKind::Block(body) => resolve_body(body, symbols, scopes)?,
+        StatementKind::Return(return_stmt) => {
+            if let Some(expr) = &mut return_stmt.expr {
+                resolve_expression(expr, symbols, scopes)?;
+            }
+        }
+        StatementKind::If(if_stmt) => {
+            for branch in &mut if_stmt.branches {
+                resolve_expression(&mut branch.condition, symbols, scopes)?;
+                resolve_body(&mut branch.body, symbols, scopes)?;
+            }
+            if let Some(else_body) = &mut if_stmt.else_body {
+                resolve_body(else_body, symbols, scopes)?;
+            }
+        }
+        StatementKind::While(while_stmt) => {
+            resolve_expression(&mut while_stmt.condition, symbols, scopes)?;
+            resolve_body(&mut while_stmt.body, symbols, scopes)?;
+        }
+    }
+    Ok(())
+}
+
+/// Binds every identifier in the syntax tree to the declaration it refers to.
+/// Functions are collected up front, so they can be called before they are declared.
+///
+/// Every function body and block opens a new scope: names are visible in nested blocks and
+/// disappear at the end of the block they were declared in. Redeclaring a name in the same
+/// scope is an error, while shadowing one from an enclosing scope is allowed.
+pub fn resolve(ast: &mut [Statement], warn_shadowing: bool) -> Result<Symbols, Diagnostic> {
+    let mut symbols = Symbols { warn_shadowing, ..Symbols::default() };
+    for stmt in ast.iter() {
+        if let StatementKind::FunctionDeclaration(function) = &stmt.kind {
+            let span = Span::new(function.name_pos.clone(), function.name_end.clone());
+            if symbols.functions.contains_key(&function.name) {
+                let first = symbols.functions[&function.name].span.clone();
+                return Err(span_error(ALREADY_DECLARED, format!("Function '{}' already declared", function.name), span)
+                    .with_label(first, format!("'{}' first declared here", function.name)));
+            }
+            symbols.functions.insert(function.name.clone(), FunctionOptions {
+                args: function.params.iter().map(|p| p.typ.clone()).collect(),
+                typ: function.typ.clone(),
+                span,
+                public: function.public,
+                used: false,
+            });
+        }
+    }
+
+    let mut scopes: Vec<Scope> = vec![Scope::default()];
+    for stmt in ast.iter_mut() {
+        resolve_statement(stmt, &mut symbols, &mut scopes)?;
+    }
+    Ok(symbols)
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+use crate::codes::{INVALID_CAST, INVALID_OPERANDS, LOSSY_CONVERSION, MISSING_RETURN_VALUE, NO_RETURN_VALUE, RETURN_OUTSIDE_FUNCTION, TYPE_MISMATCH, WRONG_ARGUMENT_COUNT};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::{error, span_error};
+use crate::evaluator::evaluate;
+use crate::lexer::TokenValue;
+use crate::parser::{CastExpression, Expression, ExpressionKind, PrimaryExpression, Statement, StatementKind, ValueType};
+use crate::resolver::Symbols;
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ValueType::Integer => "int",
+            ValueType::Float => "float",
+            ValueType::String => "str",
+            ValueType::Bool => "bool",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+fn is_numeric(typ: &ValueType) -> bool {
+    matches!(typ, ValueType::Integer | ValueType::Float)
+}
+
+fn literal_type(value: &TokenValue) -> ValueType {
+    match value {
+        TokenValue::Integer(_) => ValueType::Integer,
+        TokenValue::Float(_) => ValueType::Float,
+        TokenValue::Bool(_) => ValueType::Bool,
+        _ => ValueType::String,
+    }
+}
+
+/// Computes the type of a prefix operator applied to `operand`.
+pub fn check_unary(op: &TokenValue, operand: &ValueType, span: &Span) -> Result<ValueType, Diagnostic> {
+    let op_str = op.as_string();
+    let valid = match op_str.as_str() {
+        "-" => is_numeric(operand),
+        "!" => *operand == ValueType::Bool,
+        "~" => *operand == ValueType::Integer,
+        _ => false,
+    };
+    if !valid {
+        return Err(span_error(INVALID_OPERANDS, format!("Cannot apply unary operator '{}' to a value of type {}", op_str, operand), span.clone()));
+    }
+    Ok(operand.clone())
+}
+
+/// The type two numeric operands are brought to before an arithmetic or comparison operator is applied.
+/// An int operand is widened to float when the other operand is a float.
+pub fn common_numeric_type(left: &ValueType, right: &ValueType) -> Option<ValueType> {
+    match (left, right) {
+        (ValueType::Integer, ValueType::Integer) => Some(ValueType::Integer),
+        (ValueType::Integer | ValueType::Float, ValueType::Integer | ValueType::Float) => Some(ValueType::Float),
+        _ => None,
+    }
+}
+
+/// Checks both operands of a binary operator and computes the type of the result.
+pub fn check_binary(op: &TokenValue, left: &ValueType, right: &ValueType, span: &Span) -> Result<ValueType, Diagnostic> {
+    let op_str = op.as_string();
+    let numeric = common_numeric_type(left, right);
+    let result = match op_str.as_str() {
+        "+" | "-" | "*" | "/" | "%" if numeric.is_some() => numeric,
+        "+" if *left == ValueType::String && *right == ValueType::String => Some(ValueType::String),
+        "==" | "!=" if left == right || numeric.is_some() => Some(ValueType::Bool),
+        "<" | ">" | "<=" | ">=" if numeric.is_some() => Some(ValueType::Bool),
+        "<" | ">" | "<=" | ">=" if *left == ValueType::String && *right == ValueType::String => Some(ValueType::Bool),
+        "&" | "|" | "^" | "<<" | ">>" if *left == ValueType::Integer && *right == ValueType::Integer => Some(ValueType::Integer),
+        "&&" | "||" if *left == ValueType::Bool && *right == ValueType::Bool => Some(ValueType::Bool),
+        _ => None,
+    };
+    result.ok_or_else(|| {
+        let message = if left == right {
+            format!("Operator '{}' cannot be applied to two values of type {}", op_str, left)
+        } else {
+            format!("Mismatched types for operator '{}': left operand is {}, but right operand is {}", op_str, left, right)
+        };
+        span_error(INVALID_OPERANDS, message, span.clone())
+    })
+}
+
+/// Checks that an explicit `value as typ` conversion is defined.
+pub fn check_cast(from: &ValueType, to: &ValueType, span: &Span) -> Result<(), Diagnostic> {
+    if matches!((from, to), (ValueType::Float, ValueType::Bool) | (ValueType::Bool, ValueType::Float)) {
+        return Err(span_error(INVALID_CAST, format!("Cannot cast {} to {}, cast through int instead", from, to), span.clone()));
+    }
+    Ok(())
+}
+
+/// Wraps an already checked expression in a conversion to `typ`.
+fn wrap_cast(expr: &mut Expression, typ: &ValueType) {
+    let (pos, end) = (expr.pos.clone(), expr.end.clone());
+    let left = std::mem::replace(expr, Expression::placeholder(pos.clone(), end.clone()));
+    *expr = Expression {
+        kind: ExpressionKind::Cast(Box::from(CastExpression {
+            left,
+            typ: typ.clone(),
+        })),
+        typ: Some(typ.clone()),
+        pos,
+        end,
+    };
+}
+
+/// Converts a checked `expr` to `target` where this can be done implicitly without losing information,
+/// which is only the case for widening an int to a float.
+pub fn coerce(expr: &mut Expression, target: &ValueType) -> Result<(), Diagnostic> {
+    let Some(typ) = expr.typ.clone() else {
+        return Err(Diagnostic::error("Expression has not been type checked".to_string(), expr.pos.clone()).with_end(expr.end.clone()));
+    };
+    match (&typ, target) {
+        (from, to) if from == to => Ok(()),
+        (ValueType::Integer, ValueType::Float) => {
+            wrap_cast(expr, target);
+            Ok(())
+        }
+        (ValueType::Float, ValueType::Integer) => Err(span_error(LOSSY_CONVERSION, format!("Cannot implicitly convert {} to {} as it may lose precision, use 'as {}' to convert explicitly", typ, target, target), expr.span())),
+        _ => Err(span_error(TYPE_MISMATCH, format!("Type mismatch: expected {}, but found {}", target, typ), expr.span())),
+    }
+}
+
+/// Extends a type error with where the types of the variables involved were inferred from.
+fn explain_inferred(err: Diagnostic, exprs: &[&Expression], symbols: &Symbols) -> Diagnostic {
+    let mut err = err;
+    for expr in exprs {
+        if let ExpressionKind::Identifier(identifier) = &expr.kind {
+            let symbol = identifier.symbol.map(|id| &symbols.symbols[id]);
+            if let Some((span, typ)) = symbol.and_then(|s| s.inferred.as_ref().zip(s.typ.as_ref())) {
+                err = err.with_label(span.clone(), format!("the type of '{}' was inferred as {} from its initialiser here", identifier.name, typ));
+            }
+        }
+    }
+    err
+}
+
+/// The type of an operand that has already been checked.
+fn checked_type(expr: &Expression) -> ValueType {
+    expr.typ.clone().expect("operands are checked before the operators applied to them")
+}
+
+/// Checks an operator of a chain given the type of its left operand, returning its type and the type
+/// both operands are converted to, if any.
+fn check_operator(expr: &Expression, left_typ: &ValueType, symbols: &Symbols) -> Result<(ValueType, Option<ValueType>), Diagnostic> {
+    let span = expr.span();
+    let (left, right) = expr.operands().expect("only operators continue a chain");
+    let op = match &expr.kind {
+        ExpressionKind::Cast(cast) => {
+            check_cast(left_typ, &cast.typ, &span).map_err(|err| explain_inferred(err, &[left], symbols))?;
+            return Ok((cast.typ.clone(), None));
+        }
+        ExpressionKind::Term(term) => &term.op,
+        ExpressionKind::Binary(binary) => &binary.op,
+        ExpressionKind::Comparison(comparison) => &comparison.op,
+        ExpressionKind::Logical(logical) => &logical.op,
+        ExpressionKind::Bitwise(bitwise) => &bitwise.op,
+        _ => unreachable!("only operators continue a chain"),
+    };
+    let right = right.expect("binary operators have a right operand");
+    let right_typ = checked_type(right);
+    let typ = check_binary(op, left_typ, &right_typ, &span).map_err(|err| explain_inferred(err, &[left, right], symbols))?;
+    let operand_typ = common_numeric_type(left_typ, &right_typ).filter(|_| !matches!(op, TokenValue::Bitwise(_)));
+    Ok((typ, operand_typ))
+}
+
+/// Checks the arguments of a call, returning the return type of the function if it has one.
+fn check_call(expr: &mut Expression, symbols: &mut Symbols) -> Result<Option<ValueType>, Diagnostic> {
+    let ExpressionKind::Call(call) = &mut expr.kind else {
+        return Err(Diagnostic::error("Expected a function call".to_string(), expr.pos.clone()).with_end(expr.end.clone()));
+    };
+    let Some(function) = symbols.functions.get(&call.name).cloned() else {
+        return Err(Diagnostic::error(format!("Unknown function: '{}'", call.name), expr.pos.clone()).with_end(expr.end.clone()));
+    };
+    if call.args.len() != function.args.len() {
+        return Err(span_error(WRONG_ARGUMENT_COUNT, format!("Function '{}' expects {} argument(s), but {} were given", call.name, function.args.len(), call.args.len()), expr.span()));
+    }
+    for (arg, typ) in call.args.iter_mut().zip(&function.args) {
+        check_expression(arg, symbols)?;
+        coerce(arg, typ).map_err(|err| explain_inferred(err, &[arg], symbols))?;
+    }
+    expr.typ = function.typ.clone();
+    Ok(function.typ)
+}
+
+fn check_expression(expr: &mut Expression, symbols: &mut Symbols) -> Result<ValueType, Diagnostic> {
+    if expr.operands().is_none() {
+        return check_node(expr, symbols);
+    }
+    for operand in expr.chain_operands_mut() {
+        check_expression(operand, symbols)?;
+    }
+
+    // The operators are checked from the innermost outwards, then their types are stored and their
+    // operands converted from the outermost inwards, where each one can be reached in turn
+    let mut types = Vec::new();
+    let chain = expr.chain();
+    let mut left_typ = checked_type(chain[0]);
+    for operator in &chain[1..] {
+        let (typ, operand_typ) = check_operator(operator, &left_typ, symbols)?;
+        left_typ = typ.clone();
+        types.push((typ, operand_typ));
+    }
+    let mut node = expr;
+    while let Some((typ, operand_typ)) = types.pop() {
+        node.typ = Some(typ);
+        let (left, right) = node.operands_mut().expect("the chain continues down the left operands");
+        if let Some((left_typ, _)) = types.last() {
+            left.typ = Some(left_typ.clone());
+        }
+        let widened = operand_typ.is_some() && left.typ != operand_typ;
+        if let Some(operand_typ) = &operand_typ {
+            coerce(left, operand_typ)?;
+            coerce(right.expect("only binary operators convert their operands"), operand_typ)?;
+        }
+        // A widened left operand is wrapped in a cast, with the rest of the chain inside
+        node = if widened { left.operands_mut().expect("a widened operand is a cast").0 } else { left };
+    }
+    Ok(left_typ)
+}
+
+/// Checks an expression that is not an operator, see [`check_expression`].
+fn check_node(expr: &mut Expression, symbols: &mut Symbols) -> Result<ValueType, Diagnostic> {
+    let span = expr.span();
+    let typ = match &mut expr.kind {
+        ExpressionKind::Primary(primary) => literal_type(&primary.value),
+        ExpressionKind::Identifier(identifier) => {
+            let symbol = identifier.symbol.map(|id| &symbols.symbols[id]);
+            let Some(typ) = symbol.and_then(|s| s.typ.clone()) else {
+                return Err(Diagnostic::error(format!("Cannot determine the type of '{}'", identifier.name), span.start).with_end(span.end));
+            };
+            // Constants are inlined at every use, so later passes only ever see their value
+            if let Some(value) = symbol.and_then(|s| s.value.clone()) {
+                expr.kind = ExpressionKind::Primary(PrimaryExpression { value });
+            }
+            typ
+        }
+        ExpressionKind::Call(call) => {
+            let name = call.name.clone();
+            check_call(expr, symbols)?
+                .ok_or_else(|| span_error(NO_RETURN_VALUE, format!("Function '{}' does not return a value", name), span.clone()))?
+        }
+        ExpressionKind::Unary(unary) => {
+            let operand = check_expression(&mut unary.left, symbols)?;
+            check_unary(&unary.op, &operand, &span).map_err(|err| explain_inferred(err, &[&unary.left], symbols))?
+        }
+        ExpressionKind::Term(_) | ExpressionKind::Binary(_) | ExpressionKind::Comparison(_) | ExpressionKind::Logical(_)
+        | ExpressionKind::Bitwise(_) | ExpressionKind::Cast(_) => unreachable!("operators are checked by `check_expression`"),
+        ExpressionKind::Interpolation(interpolation) => {
+            for part in &mut interpolation.parts {
+                if check_expression(part, symbols)? != ValueType::String {
+                    wrap_cast(part, &ValueType::String);
+                }
+            }
+            ValueType::String
+        }
+    };
+    expr.typ = Some(typ.clone());
+    Ok(typ)
+}
+
+/// Checks a condition of an `if` or `while`, which must be a bool.
+fn check_condition(condition: &mut Expression, symbols: &mut Symbols) -> Result<(), Diagnostic> {
+    check_expression(condition, symbols)?;
+    coerce(condition, &ValueType::Bool).map_err(|err| explain_inferred(err, &[condition], symbols))
+}
+
+/// Checks a statement inside the body of `function`, or at the top level if it is `None`.
+fn check_statement(stmt: &mut Statement, symbols: &mut Symbols, function: Option<&str>) -> Result<(), Diagnostic> {
+    match &mut stmt.kind {
+        StatementKind::VariableDeclaration(decl) => {
+            let typ = check_expression(&mut decl.expr, symbols)?;
+            let id = decl.symbol.expect("declarations are resolved before type checking");
+            match &decl.annotation {
+                Some(annotation) => {
+                    coerce(&mut decl.expr, annotation).map_err(|err| explain_inferred(err, &[&decl.expr], symbols))?;
+                    symbols.symbols[id].typ = Some(annotation.clone());
+                }
+                None => {
+                    symbols.symbols[id].typ = Some(typ);
+                    symbols.symbols[id].inferred = Some(decl.expr.span());
+                }
+            }
+        }
+        StatementKind::ConstantDeclaration(decl) => {
+            check_expression(&mut decl.expr, symbols)?;
+            coerce(&mut decl.expr, &decl.typ)?;
+            let value = evaluate(&decl.expr)?;
+            let id = decl.symbol.expect("declarations are resolved before type checking");
+            symbols.symbols[id].typ = Some(decl.typ.clone());
+            symbols.symbols[id].value = Some(value.clone());
+            decl.value = Some(value);
+        }
+        StatementKind::FunctionDeclaration(function) => {
+            for param in &function.params {
+                let id = param.symbol.expect("declarations are resolved before type checking");
+                symbols.symbols[id].typ = Some(param.typ.clone());
+            }
+            for stmt in &mut function.body {
+                check_statement(stmt, symbols, Some(&function.name))?;
+            }
+        }
+        StatementKind::ExpressionStatement(expr_stmt) => {
+            // A call used as a statement is the only expression allowed to have no value
+            if let ExpressionKind::Call(_) = &expr_stmt.expr.kind {
+                check_call(&mut expr_stmt.expr, symbols)?;
+            } else {
+                check_expression(&mut expr_stmt.expr, symbols)?;
+            }
+        }
+        StatementKind::Block(body) => {
+            for stmt in body {
+                check_statement(stmt, symbols, function)?;
+            }
+        }
+        StatementKind::Return(return_stmt) => {
+            let Some(name) = function else {
+                return Err(error(RETURN_OUTSIDE_FUNCTION, "'return' outside of a function".to_string(), stmt.pos.clone()).with_end(stmt.end.clone()));
+            };
+            let typ = symbols.functions[name].typ.clone();
+            match (&mut return_stmt.expr, typ) {
+                (Some(expr), Some(typ)) => {
+                    check_expression(expr, symbols)?;
+                    coerce(expr, &typ).map_err(|err| explain_inferred(err, &[expr], symbols))?;
+                }
+                (Some(expr), None) => {
+                    return Err(span_error(NO_RETURN_VALUE, format!("Function '{}' does not return a value", name), expr.span()));
+                }
+                (None, Some(typ)) => {
+                    return Err(error(MISSING_RETURN_VALUE, format!("Function '{}' must return a value of type {}", name, typ), stmt.pos.clone()).with_end(stmt.end.clone()));
+                }
+                (None, None) => {}
+            }
+        }
+        StatementKind::If(if_stmt) => {
+            for branch in &mut if_stmt.branches {
+                check_condition(&mut branch.condition, symbols)?;
+                for stmt in &mut branch.body {
+                    check_statement(stmt, symbols, function)?;
+                }
+            }
+            for stmt in if_stmt.else_body.iter_mut().flatten() {
+                check_statement(stmt, symbols, function)?;
+            }
+        }
+        StatementKind::While(while_stmt) => {
+            check_condition(&mut while_stmt.condition, symbols)?;
+            for stmt in &mut while_stmt.body {
+                check_statement(stmt, symbols, function)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Computes the type of every expression, inserting implicit conversions and inlining constants.
+pub fn check(ast: &mut [Statement], symbols: &mut Symbols) -> Result<(), Diagnostic> {
+    for stmt in ast {
+        check_statement(stmt, symbols, None)?;
+    }
+    Ok(())
+}
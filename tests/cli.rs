@@ -0,0 +1,43 @@
+//! Runs the compiler binary on source files written to a temporary directory.
+
+use std::fs;
+use std::process::{Command, Output};
+
+/// Writes `source` to a file named `name` and compiles it with `args`.
+fn run(name: &str, source: &str, args: &[&str]) -> Output {
+    let dir = std::env::temp_dir().join(format!("zelkel-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).expect("failed to create the temporary directory");
+    let path = dir.join(name);
+    fs::write(&path, source).expect("failed to write the source");
+    let output = Command::new(env!("CARGO_BIN_EXE_zelkel-compiler"))
+        .args(args)
+        .arg(&path)
+        .output()
+        .expect("failed to run the compiler");
+    fs::remove_file(&path).expect("failed to remove the source");
+    output
+}
+
+#[test]
+fn compiles_a_long_chain_quietly() {
+    let source = format!("fn main() {{\n    let a = 1;\n    let _m = {};\n}}\n", vec!["a"; 10_000].join(" + "));
+    let output = run("chain.zk", &source, &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn reports_errors_and_fails() {
+    let output = run("error.zk", "fn main() {\n    let x: int = true;\n}\n", &["--error-format=json"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("\"severity\":\"error\""), "{}", stderr);
+}
+
+#[test]
+fn denies_warnings() {
+    let source = "fn main() {\n    let x = 1;\n}\n";
+    assert!(run("warning.zk", source, &[]).status.success());
+    assert_eq!(run("denied.zk", source, &["--deny-warnings"]).status.code(), Some(1));
+}
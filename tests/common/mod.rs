@@ -0,0 +1,31 @@
+use zelkel_compiler::cfg::unreachable;
+use zelkel_compiler::diagnostic::Diagnostic;
+use zelkel_compiler::lexer::lex;
+use zelkel_compiler::lint::unused;
+use zelkel_compiler::parser::parse;
+use zelkel_compiler::resolver::resolve;
+use zelkel_compiler::typechecker::check;
+
+/// Runs `source` through every pass like the compiler does, stopping at the first pass
+/// that reports an error, and returns the diagnostics reported so far.
+pub fn compile(source: &str, warn_shadowing: bool) -> Vec<Diagnostic> {
+    let (tokens, mut diagnostics) = lex(source.to_string(), "test.zk".to_string());
+    let (mut ast, errors) = parse(tokens);
+    diagnostics.extend(errors);
+    if !diagnostics.is_empty() {
+        return diagnostics;
+    }
+
+    let mut symbols = match resolve(&mut ast, warn_shadowing) {
+        Ok(symbols) => symbols,
+        Err(error) => return vec![error],
+    };
+    diagnostics.append(&mut symbols.warnings);
+    diagnostics.extend(unused(&symbols));
+    if let Err(error) = check(&mut ast, &mut symbols) {
+        diagnostics.push(error);
+        return diagnostics;
+    }
+    diagnostics.extend(unreachable(&ast));
+    diagnostics
+}
@@ -0,0 +1,66 @@
+//! Inputs that nest or chain without limit must be compiled or rejected, never overflow the stack.
+
+mod common;
+
+use common::compile;
+use zelkel_compiler::codes::{NESTED_TOO_DEEPLY, UNUSED_VARIABLE};
+use zelkel_compiler::diagnostic::Diagnostic;
+
+const ID: &str = "fn id(x: int) -> int {\n    return x;\n}\n";
+
+fn codes(diagnostics: &[Diagnostic]) -> Vec<&'static str> {
+    diagnostics.iter().filter_map(|d| d.code).collect()
+}
+
+/// Compiles `let m = <expr>;` with an int `a`, a bool `b` and a function `id` in scope.
+fn compile_expression(expr: &str) -> Vec<&'static str> {
+    codes(&compile(&format!("{ID}fn main() {{\n    let a = 1;\n    let b = a == 1;\n    let _m = {expr};\n    let _n = b || id(a) == 0;\n}}\n"), false))
+}
+
+#[test]
+fn long_else_if_chain() {
+    let arms: String = (1..10_000).map(|n| format!(" else if x == {n} {{ let y = {n}; }}")).collect();
+    let source = format!("fn main() {{\n    let x = 3;\n    if x == 0 {{ let y = 0; }}{arms}\n}}\n");
+    let diagnostics = compile(&source, false);
+    assert!(diagnostics.iter().all(|d| d.code == Some(UNUSED_VARIABLE)), "{:?}", diagnostics.first());
+}
+
+#[test]
+fn long_operator_chains() {
+    for n in [300, 50_000] {
+        assert_eq!(compile_expression(&vec!["a"; n].join(" + ")), Vec::<&str>::new());
+        assert_eq!(compile_expression(&vec!["b"; n].join(" && ")), Vec::<&str>::new());
+        assert_eq!(compile_expression(&format!("a{}", " as float as int".repeat(n))), Vec::<&str>::new());
+        // Mixing ints and floats widens every int operand to a float
+        let mixed: Vec<&str> = (0..n).map(|k| if k % 2 == 0 { "a" } else { "1.5" }).collect();
+        assert_eq!(compile_expression(&mixed.join(" + ")), Vec::<&str>::new());
+    }
+
+    let constant = format!("const C: int = {};\nfn main() {{\n    let _m = C;\n}}\n", vec!["1"; 50_000].join(" + "));
+    assert!(compile(&constant, false).is_empty());
+}
+
+#[test]
+fn expression_nesting_limit() {
+    for (depth, expected) in [(32, vec![]), (33, vec![NESTED_TOO_DEEPLY]), (10_000, vec![NESTED_TOO_DEEPLY])] {
+        assert_eq!(compile_expression(&format!("{}a{}", "(".repeat(depth), ")".repeat(depth))), expected);
+        assert_eq!(compile_expression(&format!("{}a", "- ".repeat(depth))), expected);
+        assert_eq!(compile_expression(&format!("{}a{}", "id(".repeat(depth), ")".repeat(depth))), expected);
+    }
+}
+
+#[test]
+fn block_nesting_limit() {
+    // The main function is the first of the blocks
+    for (depth, expected) in [(63, vec![]), (64, vec![NESTED_TOO_DEEPLY]), (10_000, vec![NESTED_TOO_DEEPLY])] {
+        let source = format!("fn main() {{\n    let a = 1;\n    {}let _m = a;{}\n}}\n", "if a == 1 { ".repeat(depth), " }".repeat(depth));
+        assert_eq!(codes(&compile(&source, false)), expected);
+    }
+}
+
+#[test]
+fn both_limits_at_once() {
+    let expr = format!("{}a{}", "id(".repeat(32), ")".repeat(32));
+    let source = format!("{ID}fn main() {{\n    let a = id(1);\n    {}let _m = {expr};{}\n}}\n", "while a == 1 { ".repeat(63), " }".repeat(63));
+    assert_eq!(codes(&compile(&source, false)), Vec::<&str>::new());
+}
@@ -0,0 +1,28 @@
+//! After a syntax error the parser skips to the next statement, reporting each error once.
+
+mod common;
+
+use common::compile;
+use zelkel_compiler::codes::{EXPECTED_EXPRESSION, UNEXPECTED_TOKEN};
+
+/// The code, line and column of every diagnostic reported for `source`.
+fn reported(source: &str) -> Vec<(&'static str, usize, usize)> {
+    compile(source, false)
+        .iter()
+        .map(|d| (d.code.expect("every diagnostic has a code"), d.span.start.line, d.span.start.col))
+        .collect()
+}
+
+#[test]
+fn function_header() {
+    assert_eq!(reported("fn (x: int) {}\n"), vec![(UNEXPECTED_TOKEN, 1, 4)]);
+    assert_eq!(reported("pub fn (x: int) {}\n"), vec![(UNEXPECTED_TOKEN, 1, 8)]);
+}
+
+#[test]
+fn else_if_chain() {
+    let source = "fn main() {\n    let a = true;\n    if a {} else if {}\n}\n";
+    assert_eq!(reported(source), vec![(EXPECTED_EXPRESSION, 3, 21)]);
+    let source = "fn main() {\n    let a = true;\n    if a {} else if {} else {}\n    let = 1;\n}\n";
+    assert_eq!(reported(source), vec![(EXPECTED_EXPRESSION, 3, 21), (UNEXPECTED_TOKEN, 4, 9)]);
+}
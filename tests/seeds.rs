@@ -0,0 +1,48 @@
+//! Runs every seed of the fuzzing corpus through all passes. `zNNNN.zk` is the `--explain` example
+//! of `ZNNNN` and must report it, `zNNNN_fixed.zk` is the fixed version and must report no errors.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use common::compile;
+use zelkel_compiler::diagnostic::Severity;
+
+#[test]
+fn seeds() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/seeds");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .expect("failed to read the seeds")
+        .map(|entry| entry.expect("failed to read the seeds").path())
+        .collect();
+    paths.sort();
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let name = path.file_stem().and_then(|stem| stem.to_str()).expect("seed names are UTF-8");
+        let source = fs::read_to_string(path).expect("failed to read a seed");
+        // Shadowing is only reported when asked for, which its own seed needs
+        let diagnostics = compile(&source, true);
+        let codes: Vec<&str> = diagnostics.iter().filter_map(|d| d.code).collect();
+        let has_errors = diagnostics.iter().any(|d| d.severity == Severity::Error);
+
+        let (code, fixed) = match name.strip_suffix("_fixed") {
+            Some(code) => (code.to_uppercase(), true),
+            None => (name.to_uppercase(), false),
+        };
+        let is_example = code.starts_with('Z') && code[1..].chars().all(|c| c.is_ascii_digit());
+        if !is_example {
+            if has_errors {
+                failures.push(format!("{}: reported {:?}", name, codes));
+            }
+        } else if fixed {
+            if has_errors || codes.contains(&code.as_str()) {
+                failures.push(format!("{}: reported {:?}, but is the fix for {}", name, codes, code));
+            }
+        } else if !codes.contains(&code.as_str()) {
+            failures.push(format!("{}: reported {:?}, but not {}", name, codes, code));
+        }
+    }
+    assert!(!paths.is_empty(), "no seeds in {}", dir.display());
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}